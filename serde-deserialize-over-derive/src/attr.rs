@@ -63,18 +63,12 @@ impl SerdeOption {
   }
 
   pub fn is_flag(&self) -> bool {
-    match self {
-      Self::Flag(_) => true,
-      _ => false,
-    }
+    matches!(self, Self::Flag(_))
   }
 
   #[allow(dead_code)]
   pub fn is_opt(&self) -> bool {
-    match self {
-      Self::String(_) => true,
-      _ => false,
-    }
+    matches!(self, Self::String(_))
   }
 }
 
@@ -85,11 +79,10 @@ pub(crate) struct SerdeAttrBody {
 }
 
 impl SerdeAttrBody {
-  #[allow(dead_code)]
   pub fn has(&self, name: &str) -> bool {
     for attr in &self.attrs {
       if let SerdeOption::Flag(tag) = attr {
-        if tag.to_string() == name {
+        if tag == name {
           return true;
         }
       }
@@ -101,7 +94,7 @@ impl SerdeAttrBody {
   pub fn get(&self, name: &str) -> Option<&LitStr> {
     for attr in &self.attrs {
       if let SerdeOption::String(opt) = attr {
-        if opt.ident.to_string() == name {
+        if opt.ident == name {
           return Some(&opt.value);
         }
       }
@@ -110,12 +103,21 @@ impl SerdeAttrBody {
     None
   }
 
+  /// All values given for the option `name`, for options such as `alias`
+  /// which may be repeated.
+  pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a LitStr> + 'a {
+    self.attrs.iter().filter_map(move |attr| match attr {
+      SerdeOption::String(opt) if opt.ident == name => Some(&opt.value),
+      _ => None,
+    })
+  }
+
   #[allow(dead_code)]
   pub fn span_for(&self, name: &str) -> Span {
     self
       .attrs
       .iter()
-      .find(|attr| attr.ident().to_string() == name)
+      .find(|attr| attr.ident() == name)
       .map(|attr| attr.span())
       .unwrap_or_else(Span::call_site)
   }
@@ -0,0 +1,115 @@
+//! The case conventions supported by `#[serde(rename_all = "...")]`.
+//!
+//! These follow the exact same rules as serde so that the names accepted by the
+//! `DeserializeOver` impl match those of the corresponding `Deserialize` impl.
+
+use syn::LitStr;
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub(crate) enum RenameRule {
+  #[default]
+  None,
+  LowerCase,
+  UpperCase,
+  PascalCase,
+  CamelCase,
+  SnakeCase,
+  ScreamingSnakeCase,
+  KebabCase,
+  ScreamingKebabCase,
+}
+
+static RENAME_RULES: &[(&str, RenameRule)] = &[
+  ("lowercase", RenameRule::LowerCase),
+  ("UPPERCASE", RenameRule::UpperCase),
+  ("PascalCase", RenameRule::PascalCase),
+  ("camelCase", RenameRule::CamelCase),
+  ("snake_case", RenameRule::SnakeCase),
+  ("SCREAMING_SNAKE_CASE", RenameRule::ScreamingSnakeCase),
+  ("kebab-case", RenameRule::KebabCase),
+  ("SCREAMING-KEBAB-CASE", RenameRule::ScreamingKebabCase),
+];
+
+impl RenameRule {
+  pub fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+    let value = lit.value();
+
+    RENAME_RULES
+      .iter()
+      .find(|(name, _)| *name == value)
+      .map(|(_, rule)| *rule)
+      .ok_or_else(|| {
+        let names = RENAME_RULES
+          .iter()
+          .map(|(name, _)| format!("\"{}\"", name))
+          .collect::<Vec<_>>();
+
+        syn::Error::new_spanned(
+          lit,
+          format!(
+            "unknown rename rule `rename_all = \"{}\"`, expected one of {}",
+            value,
+            names.join(", ")
+          ),
+        )
+      })
+  }
+
+  /// Apply this rule to an enum variant, which is assumed to be in PascalCase.
+  pub fn apply_to_variant(self, variant: &str) -> String {
+    match self {
+      Self::None | Self::PascalCase => variant.to_owned(),
+      Self::LowerCase => variant.to_ascii_lowercase(),
+      Self::UpperCase => variant.to_ascii_uppercase(),
+      Self::CamelCase => variant[..1].to_ascii_lowercase() + &variant[1..],
+      Self::SnakeCase => {
+        let mut snake = String::new();
+        for (i, ch) in variant.char_indices() {
+          if i > 0 && ch.is_uppercase() {
+            snake.push('_');
+          }
+          snake.push(ch.to_ascii_lowercase());
+        }
+        snake
+      }
+      Self::ScreamingSnakeCase => Self::SnakeCase
+        .apply_to_variant(variant)
+        .to_ascii_uppercase(),
+      Self::KebabCase => Self::SnakeCase.apply_to_variant(variant).replace('_', "-"),
+      Self::ScreamingKebabCase => Self::ScreamingSnakeCase
+        .apply_to_variant(variant)
+        .replace('_', "-"),
+    }
+  }
+
+  /// Apply this rule to a struct field, which is assumed to be in snake_case.
+  pub fn apply_to_field(self, field: &str) -> String {
+    match self {
+      Self::None | Self::LowerCase | Self::SnakeCase => field.to_owned(),
+      Self::UpperCase | Self::ScreamingSnakeCase => field.to_ascii_uppercase(),
+      Self::PascalCase => {
+        let mut pascal = String::new();
+        let mut capitalize = true;
+        for ch in field.chars() {
+          if ch == '_' {
+            capitalize = true;
+          } else if capitalize {
+            pascal.push(ch.to_ascii_uppercase());
+            capitalize = false;
+          } else {
+            pascal.push(ch);
+          }
+        }
+        pascal
+      }
+      Self::CamelCase => {
+        let pascal = Self::PascalCase.apply_to_field(field);
+        pascal[..1].to_ascii_lowercase() + &pascal[1..]
+      }
+      Self::KebabCase => field.replace('_', "-"),
+      Self::ScreamingKebabCase => Self::ScreamingSnakeCase
+        .apply_to_field(field)
+        .replace('_', "-"),
+    }
+  }
+}
@@ -0,0 +1,177 @@
+//! Generation of the `Diff` impl for `#[deserialize_over(diff)]`.
+//!
+//! Each field is wrapped in a helper from the export module which matches the
+//! way the field is deserialized over, so that the diff of a field is exactly
+//! what its `DeserializeOver` impl expects.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{parse_quote, DeriveInput, FieldsNamed, Ident};
+
+use crate::{
+  option_inner, wrap_impl, ContainerAttr, FieldInfo, OnNull, SetStrategy, VecStrategy, CRATE_NAME,
+};
+
+pub(crate) fn impl_diff(
+  input: &DeriveInput,
+  real_crate_name: &Ident,
+  attrs: &ContainerAttr,
+  fields: &FieldsNamed,
+  infos: &[FieldInfo],
+) -> syn::Result<TokenStream> {
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+  let export: syn::Path = parse_quote! { #crate_name::export };
+  let struct_name = &input.ident;
+  let name = attrs.name(struct_name);
+
+  let mut generics = input.generics.clone();
+  let mut diffs = Vec::new();
+  let mut vars = Vec::new();
+  let mut changed = Vec::new();
+  let mut names = Vec::new();
+
+  for (field, info) in fields.named.iter().zip(infos) {
+    if info.skip {
+      continue;
+    }
+    if info.flatten {
+      return Err(syn::Error::new(
+        info.span(),
+        "#[deserialize_over(diff)] does not support #[serde(flatten)] fields",
+      ));
+    }
+    if info.deserialize_with.is_some() || info.deserialize_merge_with.is_some() {
+      return Err(syn::Error::new(
+        info.span(),
+        "#[deserialize_over(diff)] does not support fields with #[serde(deserialize_with)]",
+      ));
+    }
+    if info.merge_by.is_some() {
+      return Err(syn::Error::new(
+        info.span(),
+        "#[deserialize_over(diff)] does not support fields with #[deserialize_over(merge_by)]",
+      ));
+    }
+
+    let ident = &field.ident;
+    let ty = &info.ty;
+    let (old, new) = (quote! { &self.#ident }, quote! { &__new.#ident });
+    let replaces = !info.passthrough
+      || info.vec == Some(VecStrategy::Replace)
+      || info.set == Some(SetStrategy::Replace);
+    let mut bounds: Vec<syn::WherePredicate> = Vec::new();
+
+    // Options which don't accept `null` as `None` can't be cleared, and those
+    // with `on_null = "clear"` can be cleared even when merged.
+    let option = match (option_inner(ty), info.on_null) {
+      (Some(inner), Some(on_null)) => Some((inner, on_null == OnNull::Clear)),
+      (Some(inner), None) if !replaces => Some((inner, false)),
+      _ => None,
+    };
+
+    let diff = if let Some((inner, clear)) = option {
+      let update = match replaces {
+        true => quote! { #export::ReplaceValues },
+        false => quote! { #export::MergeValues },
+      };
+      bounds.push(parse_quote! { #inner: #export::Serialize });
+      bounds.push(parse_quote! { #update: #export::DiffValue<#inner> });
+
+      quote! { #export::OptionDiff(#old, #new, #clear, #export::PhantomData::<#update>) }
+    } else if replaces {
+      bounds.push(parse_quote! { #ty: ::std::cmp::PartialEq + #export::Serialize });
+
+      quote! { #export::ReplaceDiff(#old, #new) }
+    } else if info.vec == Some(VecStrategy::Append) {
+      bounds.push(parse_quote! { #ty: #export::Sequence });
+      bounds.push(parse_quote! {
+        <#ty as #export::Sequence>::Item: ::std::cmp::PartialEq + #export::Serialize
+      });
+
+      quote! { #export::AppendDiff(#old, #new) }
+    } else if let Some(map) = &info.map {
+      let null = map.delete_null;
+      let tombstone = match &map.tombstone {
+        Some(tombstone) => quote! { #export::Some(#tombstone) },
+        None => quote! { #export::None },
+      };
+      let update = match map.replace_values {
+        true => quote! { #export::ReplaceValues },
+        false => quote! { #export::MergeValues },
+      };
+      bounds.push(parse_quote! { #ty: #export::MapMut });
+      bounds.push(parse_quote! { <#ty as #export::MapMut>::Key: #export::Serialize });
+      bounds.push(parse_quote! { <#ty as #export::MapMut>::Value: #export::Serialize });
+      bounds.push(parse_quote! {
+        #update: #export::DiffValue<<#ty as #export::MapMut>::Value>
+      });
+
+      quote! {
+        #export::MapDiff(
+          #old,
+          #new,
+          #export::MapDelete { null: #null, tombstone: #tombstone },
+          #export::PhantomData::<#update>,
+        )
+      }
+    } else {
+      bounds.push(parse_quote! { #ty: #crate_name::Diff });
+
+      quote! { #crate_name::Diff::diff(#old, #new) }
+    };
+
+    if !input.generics.params.is_empty() {
+      generics.make_where_clause().predicates.extend(bounds);
+    }
+
+    vars.push(Ident::new(
+      &format!("__diff{}", vars.len()),
+      Span::call_site(),
+    ));
+    changed.push(Ident::new(
+      &format!("__changed{}", changed.len()),
+      Span::call_site(),
+    ));
+    diffs.push(diff);
+    names.push(info.source_name());
+  }
+
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+  let inner = quote! {
+    #[automatically_derived]
+    impl #impl_generics #crate_name::Diff for #struct_name #ty_generics #where_clause {
+      fn differs(&self, __new: &Self) -> bool {
+        false #( || #diffs.differs() )*
+      }
+
+      fn serialize_diff<S>(&self, __new: &Self, __serializer: S) -> #export::Result<S::Ok, S::Error>
+      where
+        S: #export::Serializer
+      {
+        #(
+          let #vars = #diffs;
+          let #changed = #vars.differs();
+        )*
+
+        let mut __len = 0;
+        #(
+          if #changed {
+            __len += 1;
+          }
+        )*
+
+        let mut __state = #export::Serializer::serialize_struct(__serializer, #name, __len)?;
+        #(
+          match #changed {
+            true => #export::SerializeStruct::serialize_field(&mut __state, #names, &#vars)?,
+            false => #export::SerializeStruct::skip_field(&mut __state, #names)?,
+          }
+        )*
+        #export::SerializeStruct::end(__state)
+      }
+    }
+  };
+
+  Ok(wrap_impl(real_crate_name, inner))
+}
@@ -0,0 +1,752 @@
+//! Deriving `DeserializeOver` for enums.
+//!
+//! When the variant being deserialized matches the current variant of the
+//! enum then its fields are deserialized over the existing ones. Otherwise, a
+//! new value of the variant is deserialized from scratch and replaces the
+//! current one.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{ext::IdentExt, DataEnum, DeriveInput, Fields, Ident};
+
+use crate::{
+  deserialize_over_fn, field_identifier, fields_const_names, fresh_map_body, fresh_seq_body,
+  generic_args, merge_map_body, merge_seq_body, name_patterns, parse_attr, wrap_impl,
+  ContainerAttr, FieldInfo, UnknownFields, CRATE_NAME,
+};
+
+/// How the variant of an enum is represented in the serialized data.
+enum Tagging {
+  External,
+  Internal {
+    tag: syn::LitStr,
+  },
+  Adjacent {
+    tag: syn::LitStr,
+    content: syn::LitStr,
+  },
+  Untagged,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Style {
+  Unit,
+  Newtype,
+  Tuple,
+  Struct,
+}
+
+struct VariantInfo {
+  ident: Ident,
+  style: Style,
+  fields: Vec<FieldInfo>,
+
+  srcname: String,
+  aliases: Vec<syn::LitStr>,
+  enum_value: Ident,
+  index: usize,
+}
+
+impl VariantInfo {
+  fn source_name(&self) -> syn::LitStr {
+    syn::LitStr::new(&self.srcname, self.ident.span())
+  }
+
+  /// The name of this variant followed by all of its aliases.
+  fn accepted_names(&self) -> Vec<syn::LitStr> {
+    std::iter::once(self.source_name())
+      .chain(self.aliases.iter().cloned())
+      .collect()
+  }
+
+  fn bindings(&self) -> Vec<Ident> {
+    (0..self.fields.len())
+      .map(|idx| Ident::new(&format!("__binding{}", idx), Span::call_site()))
+      .collect()
+  }
+
+  /// A pattern which matches this variant and binds each of its fields to the
+  /// names returned by `bindings`.
+  fn pattern(&self, enum_name: &Ident) -> TokenStream {
+    let ident = &self.ident;
+    let members = self.fields.iter().map(|x| &x.member);
+    let bindings = self.bindings();
+
+    quote! { #enum_name::#ident { #( #members: #bindings ),* } }
+  }
+
+  /// An expression which constructs this variant from the fresh field values
+  /// declared by `fresh_seq_body` or `fresh_map_body`.
+  fn construct(&self, enum_name: &Ident) -> TokenStream {
+    let ident = &self.ident;
+    let members = self.fields.iter().map(|x| &x.member);
+    let values = self.fields.iter().map(|x| &x.enum_value);
+
+    quote! { #enum_name::#ident { #( #members: #values ),* } }
+  }
+
+  fn places(&self) -> Vec<TokenStream> {
+    self
+      .bindings()
+      .into_iter()
+      .map(|binding| quote! { *#binding })
+      .collect()
+  }
+
+  /// An expression which deserializes this variant from the buffered content
+  /// `content`, merging it into `self` if `self` is already this variant
+  /// unless `fresh` is set.
+  fn deserialize_content(
+    &self,
+    enum_name: &Ident,
+    export: &syn::Path,
+    content: &TokenStream,
+    fresh: bool,
+  ) -> TokenStream {
+    let de = quote! { #export::ContentDeserializer::<D::Error>::new(#content) };
+    let construct = self.construct(enum_name);
+
+    match self.style {
+      Style::Unit => quote! {{
+        <() as #export::Deserialize>::deserialize(#de)?;
+        if !__cx.is_dry_run() { *self = #construct; __cx.record_change(); }
+        Ok(())
+      }},
+      Style::Newtype => {
+        let field = &self.fields[0];
+        let value = &field.enum_value;
+        let pattern = self.pattern(enum_name);
+        let wrapper = field.build_de_wrapper(export, &self.places()[0]);
+        let seed = field.build_fresh_seed(export);
+        let replace = quote! {{
+          let #value = #export::DeserializeSeed::deserialize(#seed, #de)?;
+          if !__cx.is_dry_run() { *self = #construct; __cx.record_change(); }
+          Ok(())
+        }};
+
+        match fresh {
+          true => replace,
+          false => quote! {
+            match self {
+              #pattern => #export::DeserializeSeed::deserialize(#wrapper, #de),
+              _ => #replace,
+            }
+          },
+        }
+      }
+      Style::Tuple | Style::Struct => {
+        let visitor = self.visitor_name();
+
+        quote! {
+          #export::Deserializer::deserialize_any(#de, #visitor(&mut *self, &mut *__cx, #fresh))
+        }
+      }
+    }
+  }
+
+  fn visitor_name(&self) -> Ident {
+    Ident::new(
+      &format!("__Variant{}Visitor", self.index),
+      Span::call_site(),
+    )
+  }
+
+  fn field_ident(&self) -> Ident {
+    Ident::new(&format!("__Variant{}Field", self.index), Span::call_site())
+  }
+
+  fn fields_const(&self) -> Ident {
+    Ident::new(
+      &format!("__VARIANT{}_FIELDS", self.index),
+      Span::call_site(),
+    )
+  }
+}
+
+fn parse_variants(data: &DataEnum, attrs: &ContainerAttr) -> syn::Result<Vec<VariantInfo>> {
+  data
+    .variants
+    .iter()
+    .enumerate()
+    .map(|(index, variant)| {
+      let attr = parse_attr(variant.attrs.iter())?;
+
+      if attr.use_deserialize_over {
+        return Err(syn::Error::new_spanned(
+          &variant.ident,
+          "#[deserialize_over] can only be used on fields, not on enum variants",
+        ));
+      }
+      if attr.deserialize_fn.is_some() || attr.default.is_some() || attr.skip {
+        return Err(syn::Error::new_spanned(
+          &variant.ident,
+          "#[serde(with)], #[serde(deserialize_with)], #[serde(default)] and #[serde(skip)] are \
+           not supported on enum variants by the DeserializeOver derive macro",
+        ));
+      }
+
+      let style = match &variant.fields {
+        Fields::Unit => Style::Unit,
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Style::Newtype,
+        Fields::Unnamed(_) => Style::Tuple,
+        Fields::Named(_) => Style::Struct,
+      };
+
+      let fields = FieldInfo::from_fields(&variant.fields, attrs.rename_all_fields, attrs.on_null)?;
+      if let (false, Some(field)) = (style == Style::Struct, fields.iter().find(|x| x.flatten)) {
+        return Err(syn::Error::new(
+          field.span(),
+          "#[serde(flatten)] can only be used on named fields",
+        ));
+      }
+      if style == Style::Newtype && fields[0].skip {
+        return Err(syn::Error::new_spanned(
+          &variant.fields,
+          "the only field of a newtype variant cannot be skipped",
+        ));
+      }
+
+      Ok(VariantInfo {
+        ident: variant.ident.clone(),
+        style,
+        fields,
+        srcname: match attr.rename {
+          Some(rename) => rename.value(),
+          None => attrs
+            .rename_all
+            .apply_to_variant(&variant.ident.unraw().to_string()),
+        },
+        aliases: attr.aliases,
+        enum_value: Ident::new(&format!("__variant{}", index), variant.ident.span()),
+        index,
+      })
+    })
+    .collect()
+}
+
+/// Generate the `__Variant` identifier enum along with its `Deserialize` impl
+/// and visitor.
+fn variant_identifier(export: &syn::Path, variants: &[VariantInfo]) -> TokenStream {
+  let deserializer = Ident::new("__deserializer", Span::call_site());
+  let variant_enums = variants.iter().map(|x| &x.enum_value).collect::<Vec<_>>();
+  let (names_str, names_bytes): (Vec<_>, Vec<_>) = variants
+    .iter()
+    .map(|x| name_patterns(&x.accepted_names()))
+    .unzip();
+  let indices_u64 = (0..variants.len()).map(|x| x as u64);
+
+  let invalid_index_str = syn::LitStr::new(
+    &format!("variant index 0 <= i < {}", variants.len()),
+    Span::call_site(),
+  );
+
+  quote! {
+    #[allow(non_camel_case_types)]
+    enum __Variant {
+      #( #variant_enums, )*
+    }
+    impl<'de> #export::Deserialize<'de> for __Variant {
+      fn deserialize<D>(#deserializer: D) -> #export::Result<Self, D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        #export::Deserializer::deserialize_identifier(#deserializer, __VariantVisitor)
+      }
+    }
+
+    struct __VariantVisitor;
+    impl<'de> #export::Visitor<'de> for __VariantVisitor {
+      type Value = __Variant;
+
+      fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
+        #export::fmt::Formatter::write_str(fmt, "variant identifier")
+      }
+
+      fn visit_u64<E>(self, value: u64) -> #export::Result<Self::Value, E>
+      where
+        E: #export::Error
+      {
+        use #export::{Ok, Err};
+
+        Ok(match value {
+          #( #indices_u64 => __Variant::#variant_enums, )*
+          _ => return Err(#export::Error::invalid_value(
+            #export::Unexpected::Unsigned(value),
+            &#invalid_index_str
+          ))
+        })
+      }
+
+      fn visit_str<E>(self, value: &str) -> #export::Result<Self::Value, E>
+      where
+        E: #export::Error
+      {
+        use #export::{Ok, Err};
+
+        Ok(match value {
+          #( #names_str => __Variant::#variant_enums, )*
+          _ => return Err(#export::Error::unknown_variant(value, VARIANTS))
+        })
+      }
+
+      fn visit_bytes<E>(self, value: &[u8]) -> #export::Result<Self::Value, E>
+      where
+        E: #export::Error
+      {
+        use #export::{Ok, Err};
+
+        Ok(match value {
+          #( #names_bytes => __Variant::#variant_enums, )*
+          _ => {
+            let value = &#export::String::from_utf8_lossy(value);
+            return Err(#export::Error::unknown_variant(value, VARIANTS))
+          }
+        })
+      }
+    }
+  }
+}
+
+pub(crate) fn impl_enum(
+  mut input: DeriveInput,
+  real_crate_name: Ident,
+  attrs: ContainerAttr,
+  data: DataEnum,
+) -> syn::Result<TokenStream> {
+  let enum_name = &input.ident;
+  let name = attrs.name(enum_name);
+  let deserializer = Ident::new("__deserializer", Span::call_site());
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+  let export: syn::Path = syn::parse_quote! { #crate_name::export };
+  let deserialize_over = deserialize_over_fn(&crate_name, &export);
+
+  let variants = parse_variants(&data, &attrs)?;
+  let deny_unknown_fields = attrs.deny_unknown_fields;
+  let tagging = match (attrs.tag, attrs.content, attrs.untagged) {
+    (Some(tag), Some(content), _) => Tagging::Adjacent { tag, content },
+    (Some(tag), None, _) => Tagging::Internal { tag },
+    (None, _, true) => Tagging::Untagged,
+    (None, _, false) => Tagging::External,
+  };
+
+  if let Tagging::Internal { .. } = tagging {
+    if let Some(variant) = variants.iter().find(|x| x.style == Style::Tuple) {
+      return Err(syn::Error::new_spanned(
+        &variant.ident,
+        "#[serde(tag = \"...\")] cannot be used with tuple variants",
+      ));
+    }
+  }
+
+  // The visitor only needs the bounds required by the type itself.
+  let type_where_clause = input.generics.where_clause.clone();
+  if !input.generics.params.is_empty() {
+    let where_clause = input.generics.make_where_clause();
+
+    for field in variants.iter().flat_map(|x| x.fields.iter()) {
+      field.add_bounds(where_clause, &crate_name, true);
+    }
+  }
+
+  let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+  let impl_generics = &input.generics.params;
+  let visitor_params = generic_args(&input.generics);
+
+  let identifier = variant_identifier(&export, &variants);
+  let variant_names = variants.iter().map(|x| x.source_name());
+
+  let mut variant_visitors = Vec::new();
+  let mut arms = Vec::new();
+
+  for variant in variants.iter() {
+    let tag = &variant.enum_value;
+    let pattern = variant.pattern(enum_name);
+    let construct = variant.construct(enum_name);
+    let visitor = variant.visitor_name();
+
+    let arm = match variant.style {
+      Style::Unit => quote! {
+        (__Variant::#tag, __variant) => {
+          #export::VariantAccess::unit_variant(__variant)?;
+          if !__cx.is_dry_run() { *self.0 = #construct; __cx.record_change(); }
+          Ok(())
+        }
+      },
+      Style::Newtype => {
+        let field = &variant.fields[0];
+        let value = &field.enum_value;
+        let wrapper = field.build_de_wrapper(&export, &variant.places()[0]);
+        let seed = field.build_fresh_seed(&export);
+
+        quote! {
+          (__Variant::#tag, __variant) => match self.0 {
+            #pattern => #export::VariantAccess::newtype_variant_seed(__variant, #wrapper),
+            _ => {
+              let #value = #export::VariantAccess::newtype_variant_seed(__variant, #seed)?;
+              if !__cx.is_dry_run() { *self.0 = #construct; __cx.record_change(); }
+              Ok(())
+            }
+          }
+        }
+      }
+      Style::Tuple => {
+        let len = variant.fields.iter().filter(|x| !x.skip).count();
+
+        quote! {
+          (__Variant::#tag, __variant) => {
+            #export::VariantAccess::tuple_variant(__variant, #len, #visitor(self.0, __cx, false))
+          }
+        }
+      }
+      Style::Struct => {
+        let fields_const = variant.fields_const();
+
+        quote! {
+          (__Variant::#tag, __variant) => {
+            #export::VariantAccess::struct_variant(
+              __variant,
+              #fields_const,
+              #visitor(self.0, __cx, false),
+            )
+          }
+        }
+      }
+    };
+    arms.push(arm);
+
+    if variant.style == Style::Unit || variant.style == Style::Newtype {
+      continue;
+    }
+
+    let expecting = format!(
+      "{} variant {}::{}",
+      if variant.style == Style::Tuple {
+        "tuple"
+      } else {
+        "struct"
+      },
+      enum_name,
+      variant.ident
+    );
+    let places = variant.places();
+    let assign = quote! { if !__cx.is_dry_run() { *self.0 = #construct; __cx.record_change(); } };
+    let seq_merge = merge_seq_body(&export, &variant.fields, &places);
+    let seq_fresh = fresh_seq_body(&export, &variant.fields, &expecting, &assign);
+    let unknown = UnknownFields::new(deny_unknown_fields, &variant.fields)?;
+
+    let visit_seq = if unknown == UnknownFields::Collect {
+      quote! {}
+    } else {
+      quote! {
+        fn visit_seq<A>(self, mut seq: A) -> #export::Result<Self::Value, A::Error>
+        where
+          A: #export::SeqAccess<'de>
+        {
+          use #export::{Some, None, Error};
+          let __fresh = self.2;
+          let __cx = &mut *self.1;
+
+          match self.0 {
+            #pattern if !__fresh => { #seq_merge }
+            _ => { #seq_fresh }
+          }
+
+          Ok(())
+        }
+      }
+    };
+
+    let visit_map = if variant.style == Style::Struct {
+      let field_ident = variant.field_ident();
+      let identifier = field_identifier(&export, &field_ident, &variant.fields, false, unknown);
+      let map_merge = merge_map_body(&export, &field_ident, &variant.fields, &places, unknown);
+      let map_fresh = fresh_map_body(&export, &field_ident, &variant.fields, &assign, unknown);
+
+      quote! {
+        fn visit_map<A>(self, mut map: A) -> #export::Result<Self::Value, A::Error>
+        where
+          A: #export::MapAccess<'de>
+        {
+          use #export::{Some, None, Error};
+          let __fresh = self.2;
+          let __cx = &mut *self.1;
+
+          #identifier
+
+          match self.0 {
+            #pattern if !__fresh => { #map_merge }
+            _ => { #map_fresh }
+          }
+
+          Ok(())
+        }
+      }
+    } else {
+      quote! {}
+    };
+
+    variant_visitors.push(quote! {
+      /// Deserializes the variant, building it from scratch if the flag is set
+      /// even when the enum is already this variant.
+      struct #visitor<'a, #impl_generics>(
+        pub &'a mut #enum_name #ty_generics,
+        pub &'a mut #export::Context,
+        pub bool,
+      );
+
+      impl<'a, 'de, #impl_generics> #export::Visitor<'de> for #visitor<'a, #visitor_params>
+        #where_clause
+      {
+        type Value = ();
+
+        fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
+          #export::fmt::Formatter::write_str(fmt, #expecting)
+        }
+
+        #visit_seq
+
+        #visit_map
+      }
+    });
+  }
+
+  let body = match &tagging {
+    Tagging::External => {
+      let fields_consts = variants
+        .iter()
+        .filter(|x| x.style == Style::Struct)
+        .map(|variant| {
+          let fields_const = variant.fields_const();
+          let field_names = fields_const_names(&variant.fields);
+
+          quote! { const #fields_const: &[&str] = &[ #( #field_names, )* ]; }
+        });
+
+      quote! {
+        #identifier
+
+        struct __Visitor<'a, #impl_generics>(
+          pub &'a mut #enum_name #ty_generics,
+          pub &'a mut #export::Context,
+        ) #type_where_clause;
+
+        impl<'a, 'de, #impl_generics> #export::Visitor<'de> for __Visitor<'a, #visitor_params>
+          #where_clause
+        {
+          type Value = ();
+
+          fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
+            #export::fmt::Formatter::write_str(fmt, concat!("enum ", stringify!(#enum_name)))
+          }
+
+          fn visit_enum<A>(self, data: A) -> #export::Result<Self::Value, A::Error>
+          where
+            A: #export::EnumAccess<'de>
+          {
+            use #export::{Ok};
+            let __cx = &mut *self.1;
+
+            match #export::EnumAccess::variant(data)? {
+              #( #arms )*
+            }
+          }
+        }
+
+        #( #fields_consts )*
+
+        const VARIANTS: &[&str] = &[
+          #( #variant_names, )*
+        ];
+
+        #export::Deserializer::deserialize_enum(
+          #deserializer,
+          #name,
+          VARIANTS,
+          __Visitor(self, __cx)
+        )
+      }
+    }
+    Tagging::Internal { tag } => {
+      let expecting = format!("internally tagged enum {}", enum_name);
+      let content = quote! { __content };
+      let arms = variants.iter().map(|variant| {
+        let tag = &variant.enum_value;
+        let body = match variant.style {
+          // Any other fields next to the tag are ignored, same as serde.
+          Style::Unit => {
+            let construct = variant.construct(enum_name);
+            quote! {{ if !__cx.is_dry_run() { *self = #construct; __cx.record_change(); } Ok(()) }}
+          }
+          _ => variant.deserialize_content(enum_name, &export, &content, false),
+        };
+
+        quote! { __Variant::#tag => #body, }
+      });
+
+      quote! {
+        #identifier
+
+        const VARIANTS: &[&str] = &[
+          #( #variant_names, )*
+        ];
+
+        let __content =
+          <#export::Content<'de> as #export::Deserialize<'de>>::deserialize(#deserializer)?;
+        let (__tag, __content) =
+          #export::take_internal_tag::<__Variant, D::Error>(__content, #tag, #expecting)?;
+
+        match __tag {
+          #( #arms )*
+        }
+      }
+    }
+    Tagging::Adjacent {
+      tag,
+      content: content_key,
+    } => {
+      let expecting = format!("adjacently tagged enum {}", enum_name);
+      let content = quote! { __content };
+      let arms = variants.iter().map(|variant| {
+        let tag = &variant.enum_value;
+        let body = variant.deserialize_content(enum_name, &export, &content, false);
+        let body = match variant.style {
+          Style::Unit => quote! {{
+            let __content = __content.unwrap_or(#export::Content::Unit);
+            #body
+          }},
+          _ => quote! {{
+            let __content = match __content {
+              Some(content) => content,
+              None => return Err(<D::Error as #export::Error>::missing_field(#content_key)),
+            };
+            #body
+          }},
+        };
+
+        quote! { __Variant::#tag => #body, }
+      });
+
+      quote! {
+        #identifier
+
+        const VARIANTS: &[&str] = &[
+          #( #variant_names, )*
+        ];
+
+        let __content =
+          <#export::Content<'de> as #export::Deserialize<'de>>::deserialize(#deserializer)?;
+        let (__tag, __content) = #export::take_adjacent_tag::<__Variant, D::Error>(
+          __content,
+          #tag,
+          #content_key,
+          #expecting
+        )?;
+
+        match __tag {
+          #( #arms )*
+        }
+      }
+    }
+    Tagging::Untagged => {
+      let variant_enums = variants.iter().map(|x| &x.enum_value).collect::<Vec<_>>();
+      let variant_idents = variants.iter().map(|x| &x.ident);
+      let content = quote! { __content.clone() };
+      let merges = variants
+        .iter()
+        .map(|variant| variant.deserialize_content(enum_name, &export, &content, false))
+        .collect::<Vec<_>>();
+      let attempts = variants
+        .iter()
+        .map(|variant| variant.deserialize_content(enum_name, &export, &content, true))
+        .collect::<Vec<_>>();
+      let message = format!(
+        "data did not match any variant of untagged enum {}",
+        enum_name
+      );
+
+      quote! {
+        #[allow(non_camel_case_types)]
+        enum __Variant {
+          #( #variant_enums, )*
+        }
+
+        let __content =
+          <#export::Content<'de> as #export::Deserialize<'de>>::deserialize(#deserializer)?;
+
+        // Errors from the attempts must propagate for them to be told apart,
+        // so stop collecting them until a variant has been picked.
+        let __collect = __cx.set_collecting_errors(false);
+        let __result = (|| -> #export::Result<(), D::Error> {
+          let __current = match self {
+            #( #enum_name::#variant_idents { .. } => __Variant::#variant_enums, )*
+          };
+
+          // The input is merged into the current variant only if a dry run
+          // succeeds and uses every key. Otherwise the variants are built from
+          // scratch in order, the same as serde.
+          let mut __probe = __cx.probe();
+          let __fits = {
+            let __cx = &mut __probe;
+            match __current {
+              #(
+                __Variant::#variant_enums => (|| -> #export::Result<(), D::Error> {
+                  #merges
+                })(),
+              )*
+            }
+          };
+          if __fits.is_ok() && __probe.unknown_keys().is_empty() {
+            return match __current {
+              #(
+                __Variant::#variant_enums => (|| -> #export::Result<(), D::Error> {
+                  #merges
+                })(),
+              )*
+            };
+          }
+
+          let __unknown_keys = __cx.unknown_keys().len();
+          #(
+            let __result = (|| -> #export::Result<(), D::Error> { #attempts })();
+            if __result.is_ok() {
+              return Ok(());
+            }
+            __cx.truncate_unknown_keys(__unknown_keys);
+          )*
+
+          // The path of any error from the attempts above no longer applies.
+          __cx.clear_error_path();
+          Err(<D::Error as #export::Error>::custom(#message))
+        })();
+        __cx.set_collecting_errors(__collect);
+        __result
+      }
+    }
+  };
+
+  let inner = quote! {
+    #[automatically_derived]
+    impl<'de, #impl_generics> #crate_name::DeserializeOver<'de> for #enum_name #ty_generics
+      #where_clause
+    {
+      #deserialize_over
+
+      fn deserialize_over_with<D>(
+        &mut self,
+        #deserializer: D,
+        __cx: &mut #export::Context,
+      ) -> #export::Result<(), D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        use #export::{Some, None, Ok, Err};
+
+        #( #variant_visitors )*
+
+        #body
+      }
+    }
+  };
+
+  Ok(wrap_impl(&real_crate_name, inner))
+}
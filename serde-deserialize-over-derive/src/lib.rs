@@ -5,6 +5,10 @@
 extern crate proc_macro;
 
 mod attr;
+mod case;
+mod diff;
+mod enums;
+mod patch;
 
 use std::collections::HashSet;
 
@@ -13,10 +17,14 @@ use proc_macro2::{Span, TokenStream};
 use proc_macro_crate::{crate_name, FoundCrate};
 use quote::{quote, ToTokens};
 use syn::{
-  parse_macro_input, parse_quote, punctuated::Punctuated, Attribute, Data, DeriveInput, Fields,
-  FieldsNamed, FieldsUnnamed, GenericParam, Ident, Path, Token, Type,
+  ext::IdentExt, parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned,
+  Attribute, Data, DeriveInput, Field, Fields, FieldsNamed, FieldsUnnamed, GenericParam, Generics,
+  Ident, Member, Path, Token, Type,
 };
 
+use self::attr::SerdeOption;
+use self::case::RenameRule;
+
 const CRATE_NAME: &str = "serde_deserialize_over";
 
 /// Derive macro for the `DeserializeOver` trait.
@@ -30,16 +38,19 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     FoundCrate::Itself => Ident::new(CRATE_NAME, Span::call_site()),
   };
 
-  let data = match input.data {
-    Data::Struct(ref data) => data.clone(),
-    Data::Enum(_) => panic!("`DeserializeOver` cannot be automatically derived for enums"),
-    Data::Union(_) => panic!("`DeserializeOver` cannot be automatically derived for unions"),
+  let attrs = match parse_container_attr(&input) {
+    Ok(attrs) => attrs,
+    Err(e) => return e.to_compile_error().into(),
   };
 
-  let res = match data.fields {
-    Fields::Named(fields) => impl_named_fields(input, crate_name, fields),
-    Fields::Unnamed(fields) => impl_unnamed_fields(input, crate_name, fields),
-    Fields::Unit => impl_unit(input, crate_name),
+  let res = match input.data.clone() {
+    Data::Struct(data) => match data.fields {
+      Fields::Named(fields) => impl_named_fields(input, crate_name, attrs, fields),
+      Fields::Unnamed(fields) => impl_unnamed_fields(input, crate_name, attrs, fields),
+      Fields::Unit => impl_unit(input, crate_name, attrs),
+    },
+    Data::Enum(data) => self::enums::impl_enum(input, crate_name, attrs, data),
+    Data::Union(_) => panic!("`DeserializeOver` cannot be automatically derived for unions"),
   };
 
   match res {
@@ -51,28 +62,318 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
   }
 }
 
+/// How a `#[deserialize_over]` field containing a `Vec` is updated.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum VecStrategy {
+  /// Replace the whole vector.
+  Replace,
+  /// Append the new elements to the end of the vector.
+  Append,
+  /// Deserialize element N over the existing element N, pushing any extra
+  /// elements.
+  ByIndex,
+}
+
+impl VecStrategy {
+  fn from_lit(lit: &syn::LitStr) -> syn::Result<Self> {
+    match &*lit.value() {
+      "replace" => Ok(Self::Replace),
+      "append" => Ok(Self::Append),
+      "by_index" => Ok(Self::ByIndex),
+      other => Err(syn::Error::new_spanned(
+        lit,
+        format!(
+          r#"unknown vec strategy `{}`, expected one of "replace", "append" or "by_index""#,
+          other
+        ),
+      )),
+    }
+  }
+}
+
+/// How a `#[deserialize_over]` field containing a set is updated.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum SetStrategy {
+  /// Add the new elements to the set.
+  Union,
+  /// Replace the whole set.
+  Replace,
+}
+
+impl SetStrategy {
+  fn from_lit(lit: &syn::LitStr) -> syn::Result<Self> {
+    match &*lit.value() {
+      "union" => Ok(Self::Union),
+      "replace" => Ok(Self::Replace),
+      other => Err(syn::Error::new_spanned(
+        lit,
+        format!(
+          r#"unknown set strategy `{}`, expected one of "union" or "replace""#,
+          other
+        ),
+      )),
+    }
+  }
+}
+
+/// How the entries of a map field are updated.
+#[derive(Clone, Default)]
+struct MapOptions {
+  /// Remove entries whose value is `null`.
+  delete_null: bool,
+  /// Remove entries whose value is this string.
+  tombstone: Option<syn::LitStr>,
+  /// Replace existing values instead of deserializing over them.
+  replace_values: bool,
+}
+
+/// What happens when a field is `null` in the input.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum OnNull {
+  /// Leave the current value as is.
+  Keep,
+  /// Set the field to `None`.
+  Clear,
+  /// Report an error.
+  Error,
+}
+
+impl OnNull {
+  fn from_lit(lit: &syn::LitStr) -> syn::Result<Self> {
+    match &*lit.value() {
+      "keep" => Ok(Self::Keep),
+      "clear" => Ok(Self::Clear),
+      "error" => Ok(Self::Error),
+      other => Err(syn::Error::new_spanned(
+        lit,
+        format!(
+          r#"unknown on_null behaviour `{}`, expected one of "keep", "clear" or "error""#,
+          other
+        ),
+      )),
+    }
+  }
+}
+
+/// Whether `ty` is syntactically an `Option`.
+fn is_option(ty: &Type) -> bool {
+  match ty {
+    Type::Path(ty) => match ty.path.segments.last() {
+      Some(segment) => segment.ident == "Option",
+      None => false,
+    },
+    _ => false,
+  }
+}
+
+/// The type inside `ty` if it is syntactically an `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+  let segment = match ty {
+    Type::Path(ty) => ty.path.segments.last()?,
+    _ => return None,
+  };
+  let args = match &segment.arguments {
+    syn::PathArguments::AngleBracketed(args) if segment.ident == "Option" => &args.args,
+    _ => return None,
+  };
+
+  match args.first() {
+    Some(syn::GenericArgument::Type(inner)) if args.len() == 1 => Some(inner),
+    _ => None,
+  }
+}
+
+/// Options for merging the elements of a `Vec` by a key field.
+#[derive(Clone)]
+struct MergeBy {
+  /// The key field as it appears in the input.
+  key: syn::LitStr,
+  /// The key field within the element type.
+  member: Member,
+  tombstone: Option<syn::LitStr>,
+}
+
+/// The value used for a field that is missing when building a fresh instance.
+#[derive(Clone)]
+enum FieldDefault {
+  Default,
+  Path(Path),
+}
+
 #[derive(Clone)]
 struct FieldInfo {
-  name: Ident,
+  member: Member,
   ty: Type,
   passthrough: bool,
+  vec: Option<VecStrategy>,
+  set: Option<SetStrategy>,
+  merge_by: Option<MergeBy>,
+  map: Option<MapOptions>,
+  on_null: Option<OnNull>,
   deserialize_with: Option<Path>,
   deserialize_merge_with: Option<Path>,
+  default: Option<FieldDefault>,
+  skip: bool,
+  flatten: bool,
 
   srcname: Option<String>,
+  aliases: Vec<syn::LitStr>,
   enum_value: Ident,
 }
 
 impl FieldInfo {
-  fn build_de_wrapper(&self, export: &syn::Path) -> TokenStream {
-    let Self { name, ty, .. } = self;
-    let visname = Ident::new(&format!("FieldWrapper{}", self.enum_value), name.span());
+  /// Parse the attributes of `fields`, naming any fields without an explicit
+  /// `#[serde(rename)]` according to `rename_all`. `on_null` is the container
+  /// default for `Option` fields.
+  fn from_fields<'a, I>(
+    fields: I,
+    rename_all: RenameRule,
+    on_null: Option<OnNull>,
+  ) -> syn::Result<Vec<Self>>
+  where
+    I: IntoIterator<Item = &'a Field>,
+  {
+    fields
+      .into_iter()
+      .enumerate()
+      .map(|(idx, x)| {
+        let attr = parse_attr(x.attrs.iter())?;
+        if let (Some((OnNull::Clear, span)), false) = (attr.on_null, is_option(&x.ty)) {
+          return Err(syn::Error::new(
+            span,
+            "#[deserialize_over(on_null = \"clear\")] can only be used on `Option` fields",
+          ));
+        }
+
+        let member = match &x.ident {
+          Some(ident) => Member::Named(ident.clone()),
+          None => Member::Unnamed(syn::Index {
+            index: idx as u32,
+            span: x.ty.span(),
+          }),
+        };
+
+        Ok(FieldInfo {
+          enum_value: Ident::new(&format!("__field{}", idx), member.span()),
+
+          member,
+          ty: x.ty.clone(),
+          passthrough: attr.use_deserialize_over,
+          vec: attr.vec.map(|(strategy, _)| strategy),
+          set: attr.set,
+          merge_by: attr.merge_by,
+          map: attr.map,
+          on_null: match attr.on_null {
+            None if is_option(&x.ty) => on_null,
+            on_null => on_null.map(|(on_null, _)| on_null),
+          },
+          deserialize_with: attr.deserialize_fn,
+          deserialize_merge_with: attr.deserialize_merge_fn,
+          // Skipped fields are always missing when building a fresh instance.
+          default: match attr.default {
+            None if attr.skip => Some(FieldDefault::Default),
+            default => default,
+          },
+          skip: attr.skip,
+          flatten: attr.flatten && !attr.skip,
+          srcname: match (attr.rename, &x.ident) {
+            (Some(rename), _) => Some(rename.value()),
+            (None, Some(ident)) => Some(rename_all.apply_to_field(&ident.unraw().to_string())),
+            (None, None) => None,
+          },
+          aliases: attr.aliases,
+        })
+      })
+      .collect()
+  }
+
+  fn span(&self) -> Span {
+    self.member.span()
+  }
+
+  /// Whether the existing value is updated through its `DeserializeOver` impl.
+  fn merges(&self) -> bool {
+    let replace_values = matches!(&self.map, Some(map) if map.replace_values);
+
+    self.passthrough
+      && !replace_values
+      && self.set != Some(SetStrategy::Replace)
+      && !matches!(self.vec, Some(VecStrategy::Replace | VecStrategy::Append))
+  }
+
+  /// Build a `DeserializeSeed` which deserializes over the value at `place`.
+  ///
+  /// The seed borrows the `Context` bound to `__cx`.
+  fn build_de_wrapper(&self, export: &syn::Path, place: &TokenStream) -> TokenStream {
+    let Self { ty, .. } = self;
+    let visname = Ident::new(&format!("FieldWrapper{}", self.enum_value), self.span());
     let lt = syn::Lifetime::new("'_serde_deserialize_over_a", Span::call_site());
 
+    match (self.vec, self.set) {
+      (Some(VecStrategy::Replace), _) | (_, Some(SetStrategy::Replace)) => {
+        return quote! { #export::DeserializeWrapper(&mut #place, &mut *__cx) }
+      }
+      (Some(VecStrategy::Append), _) => {
+        return quote! { #export::VecAppendWrapper(&mut #place, &mut *__cx) }
+      }
+      _ => (),
+    }
+
+    if let Some(MergeBy {
+      key,
+      member,
+      tombstone,
+    }) = &self.merge_by
+    {
+      let tombstone = match tombstone {
+        Some(tombstone) => quote! { #export::Some(#tombstone) },
+        None => quote! { #export::None },
+      };
+
+      return quote! {
+        #export::VecMergeByWrapper::new(
+          &mut #place,
+          &mut *__cx,
+          #key,
+          #tombstone,
+          |__item| &__item.#member,
+        )
+      };
+    }
+
+    if let Some(map) = &self.map {
+      let null = map.delete_null;
+      let tombstone = match &map.tombstone {
+        Some(tombstone) => quote! { #export::Some(#tombstone) },
+        None => quote! { #export::None },
+      };
+      let update = match map.replace_values {
+        true => quote! { #export::ReplaceValues },
+        false => quote! { #export::MergeValues },
+      };
+
+      return quote! {
+        #export::MapWrapper(
+          &mut #place,
+          &mut *__cx,
+          #export::MapDelete {
+            null: #null,
+            tombstone: #tombstone,
+          },
+          #export::PhantomData::<#update>,
+        )
+      };
+    }
+
     if self.passthrough {
       if let Some(merge_fn) = &self.deserialize_merge_with {
+        let dry_run_msg = format!(
+          "field `{}` does not support dry runs",
+          self.source_name().value()
+        );
+
         quote::quote! {{
-          struct #visname<#lt>(&#lt mut #ty);
+          struct #visname<#lt>(&#lt mut #ty, &#lt mut #export::Context);
 
           impl<'de> #export::DeserializeSeed<'de> for #visname<'_> {
             type Value = ();
@@ -81,94 +382,298 @@ impl FieldInfo {
             where
                 D: #export::Deserializer<'de>
             {
-              #merge_fn(deserializer, self.0)
+              if self.1.is_dry_run() {
+                return #export::Err(<D::Error as #export::Error>::custom(#dry_run_msg));
+              }
+
+              #merge_fn(deserializer, self.0)?;
+              self.1.record_change();
+              Ok(())
             }
           }
 
-          #visname(&mut (self.0).#name)
+          #visname(&mut #place, &mut *__cx)
         }}
       } else {
         if self.deserialize_with.is_some() {
           return quote::quote_spanned! {
-            name.span() => {
+            self.span() => {
               compile_error!(r#"Field uses both $[serde(deserialize_with)] and #[deserializer_over]. Use #[serde(with = "...")] so that the DeserializeOver derive will use a custom deserialize function."#);
               unreachable!()
             }
           };
         }
 
-        quote! { #export::DeserializeOverWrapper(&mut (self.0).#name) }
+        quote! { #export::DeserializeOverWrapper(&mut #place, &mut *__cx) }
       }
-    } else {
-      if let Some(de_fn) = &self.deserialize_with {
-        quote::quote! {{
-          struct #visname<#lt>(&#lt mut #ty);
+    } else if let Some(de_fn) = &self.deserialize_with {
+      quote::quote! {{
+        struct #visname<#lt>(&#lt mut #ty, &#lt mut #export::Context);
 
-          impl<'de> #export::DeserializeSeed<'de> for #visname<'_> {
-            type Value = ();
+        impl<'de> #export::DeserializeSeed<'de> for #visname<'_> {
+          type Value = ();
 
-            fn deserialize<D>(self, deserializer: D) -> #export::Result<Self::Value, D::Error>
-            where
-                D: #export::Deserializer<'de>
-            {
-              *self.0 = #de_fn(deserializer)?;
-              Ok(())
+          fn deserialize<D>(self, deserializer: D) -> #export::Result<Self::Value, D::Error>
+          where
+              D: #export::Deserializer<'de>
+          {
+            let value = #de_fn(deserializer)?;
+            if !self.1.is_dry_run() {
+              *self.0 = value;
+              self.1.record_change();
             }
+
+            Ok(())
           }
+        }
 
-          #visname(&mut (self.0).#name)
-        }}
-      } else {
-        quote! { #export::DeserializeWrapper(&mut (self.0).#name) }
-      }
+        #visname(&mut #place, &mut *__cx)
+      }}
+    } else {
+      quote! { #export::DeserializeWrapper(&mut #place, &mut *__cx) }
     }
   }
 
-  fn map_de(&self, export: &syn::Path) -> TokenStream {
-    let wrapper = self.build_de_wrapper(export);
-    quote! { map.next_value_seed(#wrapper)? }
+  /// Build a `DeserializeSeed` which produces a new value for this field.
+  fn build_fresh_seed(&self, export: &syn::Path) -> TokenStream {
+    let Self { ty, .. } = self;
+    let visname = Ident::new(&format!("FreshWrapper{}", self.enum_value), self.span());
+
+    match &self.deserialize_with {
+      Some(de_fn) => quote::quote! {{
+        struct #visname;
+
+        impl<'de> #export::DeserializeSeed<'de> for #visname {
+          type Value = #ty;
+
+          fn deserialize<D>(self, deserializer: D) -> #export::Result<Self::Value, D::Error>
+          where
+              D: #export::Deserializer<'de>
+          {
+            #de_fn(deserializer)
+          }
+        }
+
+        #visname
+      }},
+      None => quote! { #export::PhantomData::<#ty> },
+    }
   }
 
-  fn seq_de(&self, export: &syn::Path) -> TokenStream {
-    let wrapper = self.build_de_wrapper(export);
+  /// The expression used for this field when it is absent while building a
+  /// fresh instance.
+  fn missing_value(&self, export: &syn::Path) -> TokenStream {
+    let ty = &self.ty;
+    let name = self.source_name();
+
+    match &self.default {
+      Some(FieldDefault::Default) => quote! { #export::Default::default() },
+      Some(FieldDefault::Path(path)) => quote! { #path() },
+      None if self.deserialize_with.is_some() => quote! {
+        return #export::Err(<A::Error as #export::Error>::missing_field(#name))
+      },
+      None => quote! { #export::missing_field::<#ty, A::Error>(#name)? },
+    }
+  }
+
+  fn map_de(&self, export: &syn::Path, place: &TokenStream) -> TokenStream {
+    let wrapper = self.build_de_wrapper(export, place);
+    let name = self.source_name();
+
+    // Decide whether null needs to be intercepted and what it should do.
+    let (check, on_null) = match (self.on_null, &self.default) {
+      (Some(OnNull::Keep), _) => (quote! { true }, quote! { #export::Ok(()) }),
+      (Some(OnNull::Clear), _) => (
+        quote! { true },
+        quote! {{
+          if #place.is_some() && !__cx.is_dry_run() {
+            #place = #export::None;
+            __cx.record_change();
+          }
+          #export::Ok(())
+        }},
+      ),
+      (Some(OnNull::Error), _) => {
+        let message = format!("field `{}` cannot be null", name.value());
+        (
+          quote! { true },
+          quote! { #export::Err(<A::Error as #export::Error>::custom(#message)) },
+        )
+      }
+      // In a merge patch null resets fields with a default to that default.
+      (None, Some(default)) => {
+        let default = match default {
+          FieldDefault::Default => quote! { #export::Default::default() },
+          FieldDefault::Path(path) => quote! { #path() },
+        };
+
+        (
+          quote! { __cx.is_merge_patch() },
+          quote! {{
+            if !__cx.is_dry_run() {
+              #place = #default;
+              __cx.record_change();
+            }
+            #export::Ok(())
+          }},
+        )
+      }
+      (None, None) if self.deserialize_with.is_some() || self.deserialize_merge_with.is_some() => {
+        (quote! { false }, quote! { #export::Ok(()) })
+      }
+      // Otherwise null resets the field to `Default::default()` if its type has
+      // one.
+      (None, None) => {
+        let message = format!("field `{}` has no default to reset to", name.value());
+
+        (
+          quote! { __cx.is_merge_patch() },
+          quote! {{
+            use #export::{ResetWithDefault as _, ResetWithoutDefault as _};
+
+            match (&#export::ResetDefault::of(&#place)).reset() {
+              #export::Some(__value) => {
+                if !__cx.is_dry_run() {
+                  #place = __value;
+                  __cx.record_change();
+                }
+                #export::Ok(())
+              }
+              #export::None => #export::Err(<A::Error as #export::Error>::custom(#message)),
+            }
+          }},
+        )
+      }
+    };
+
+    // When collecting errors the value is buffered so that a failure can be
+    // recorded and skipped without losing our place in the input.
+    quote! {{
+      __cx.push_field(#name);
+      let __collect = __cx.is_collecting_errors();
+      let __seed = #export::RecoverSeed(__collect, #export::NullSeed(#check, #wrapper));
+      let __result = match map.next_value_seed(__seed) {
+        #export::Ok(#export::Ok(#export::Some(_))) => #export::Ok(()),
+        #export::Ok(#export::Ok(#export::None)) => {
+          let __result = #on_null;
+          __cx.recover(__result)
+        }
+        #export::Ok(#export::Err(__message)) => {
+          __cx.record_error(__message);
+          #export::Ok(())
+        }
+        #export::Err(__error) => #export::Err(__error),
+      };
+      __cx.pop_result(__result)?
+    }}
+  }
+
+  fn seq_de(&self, export: &syn::Path, place: &TokenStream) -> TokenStream {
+    let wrapper = self.build_de_wrapper(export, place);
+    let name = self.source_name();
     quote! {
-      if seq.next_element_seed(#wrapper)?.is_none() {
+      __cx.push_field(#name);
+      let __result = seq.next_element_seed(#wrapper);
+      if __cx.pop_result(__result)?.is_none() {
         return Ok(())
       }
     }
   }
 
   fn source_name(&self) -> syn::LitStr {
-    match &self.srcname {
-      Some(name) => syn::LitStr::new(&name, self.name.span()),
-      None => syn::LitStr::new(&self.name.to_string(), self.name.span()),
+    match (&self.srcname, &self.member) {
+      (Some(name), _) => syn::LitStr::new(name, self.span()),
+      (None, Member::Named(name)) => syn::LitStr::new(&name.to_string(), name.span()),
+      (None, Member::Unnamed(index)) => syn::LitStr::new(&index.index.to_string(), index.span),
+    }
+  }
+
+  /// Whether this field is deserialized from its own key in a map.
+  fn is_keyed(&self) -> bool {
+    !self.skip && !self.flatten
+  }
+
+  /// The name of this field followed by all of its aliases.
+  fn accepted_names(&self) -> Vec<syn::LitStr> {
+    std::iter::once(self.source_name())
+      .chain(self.aliases.iter().cloned())
+      .collect()
+  }
+
+  /// Add the trait bounds needed to deserialize this field to a where clause.
+  ///
+  /// If `fresh` is set then the field also needs to be able to create new
+  /// values and not just deserialize over existing ones.
+  fn add_bounds(&self, where_clause: &mut syn::WhereClause, crate_name: &Ident, fresh: bool) {
+    let ty = &self.ty;
+
+    // Skipped fields only need their default when creating a fresh value while
+    // other fields may also be reset to it by a merge patch.
+    if let (Some(FieldDefault::Default), true) = (&self.default, fresh || !self.skip) {
+      where_clause.predicates.push(parse_quote! {
+        #ty: #crate_name::export::Default
+      });
+    }
+
+    if self.skip {
+      return;
+    }
+
+    if self.merges() && self.deserialize_merge_with.is_none() {
+      where_clause.predicates.push(parse_quote! {
+        #ty: #crate_name::DeserializeOver<'de>
+      });
+    }
+
+    if (!self.merges() || fresh) && self.deserialize_with.is_none() {
+      where_clause.predicates.push(parse_quote! {
+        #ty: #crate_name::export::Deserialize<'de>
+      });
     }
   }
 }
 
-fn impl_generic(
-  mut input: DeriveInput,
-  real_crate_name: Ident,
-  fields: Vec<FieldInfo>,
+/// How keys which don't match any field are handled.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum UnknownFields {
+  /// Skip over the value.
+  Ignore,
+  /// Report an `unknown_field` error.
+  Deny,
+  /// Buffer the entry so that it can be passed on to `#[serde(flatten)]`
+  /// fields.
+  Collect,
+}
+
+impl UnknownFields {
+  fn new(deny_unknown_fields: bool, fields: &[FieldInfo]) -> syn::Result<Self> {
+    match (deny_unknown_fields, fields.iter().find(|x| x.flatten)) {
+      (true, Some(field)) => Err(syn::Error::new(
+        field.span(),
+        "#[serde(flatten)] cannot be used together with #[serde(deny_unknown_fields)]",
+      )),
+      (_, Some(_)) => Ok(Self::Collect),
+      (true, None) => Ok(Self::Deny),
+      (false, None) => Ok(Self::Ignore),
+    }
+  }
+}
+
+/// Generate the field identifier enum `ident` along with its `Deserialize`
+/// impl and visitor.
+fn field_identifier(
+  export: &syn::Path,
+  ident: &Ident,
+  fields: &[FieldInfo],
   fields_numbered: bool,
-) -> syn::Result<TokenStream> {
-  let struct_name = &input.ident;
+  unknown: UnknownFields,
+) -> TokenStream {
   let deserializer = Ident::new("__deserializer", Span::call_site());
-  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
-  let export = syn::parse_quote! { #crate_name::export };
-
-  let field_enums = fields
-    .iter()
-    .map(|field| &field.enum_value)
-    .cloned()
-    .collect::<Vec<_>>();
-  let field_enums = &field_enums;
-  let field_enums_copy1 = field_enums;
-  let field_enums_copy2 = field_enums;
+  let visitor = Ident::new(&format!("{}Visitor", ident), Span::call_site());
+  let fields = fields.iter().filter(|x| x.is_keyed()).collect::<Vec<_>>();
+  let field_enums = fields.iter().map(|x| &x.enum_value).collect::<Vec<_>>();
   let field_names = fields.iter().map(|x| x.source_name()).collect::<Vec<_>>();
-  let field_names = &field_names;
-  let indices = (0usize..fields.len()).collect::<Vec<_>>();
-  let indices_u64 = indices.iter().map(|x| *x as u64);
+  let indices_u64 = (0..fields.len()).map(|x| x as u64);
 
   let missing_field_error_str = syn::LitStr::new(
     &format!("field index between 0 <= i < {}", fields.len()),
@@ -176,11 +681,40 @@ fn impl_generic(
   );
 
   let visit_str_and_bytes_impl = if !fields_numbered {
-    let names_str = &field_names;
-    let names_bytes = field_names
+    let (names_str, names_bytes): (Vec<_>, Vec<_>) = fields
       .iter()
-      .map(|x| syn::LitByteStr::new(x.value().as_bytes(), x.span()))
-      .collect::<Vec<_>>();
+      .map(|x| name_patterns(&x.accepted_names()))
+      .unzip();
+
+    let (unknown_str, unknown_bytes) = match unknown {
+      UnknownFields::Ignore => (
+        quote! {
+          match self.0 {
+            true => #ident::__unknown(#export::String::from(value)),
+            false => #ident::__ignore,
+          }
+        },
+        quote! {
+          match self.0 {
+            true => #ident::__unknown(#export::String::from_utf8_lossy(value).into_owned()),
+            false => #ident::__ignore,
+          }
+        },
+      ),
+      UnknownFields::Deny => (
+        quote! {
+          return #export::Err(#export::Error::unknown_field(value, &[ #( #field_names ),* ]))
+        },
+        quote! {{
+          let value = &#export::String::from_utf8_lossy(value);
+          return #export::Err(#export::Error::unknown_field(value, &[ #( #field_names ),* ]))
+        }},
+      ),
+      UnknownFields::Collect => (
+        quote! { #ident::__other(#export::Content::String(#export::String::from(value))) },
+        quote! { #ident::__other(#export::Content::ByteBuf(value.to_vec())) },
+      ),
+    };
 
     quote! {
       fn visit_str<E>(self, value: &str) -> #export::Result<Self::Value, E>
@@ -188,8 +722,8 @@ fn impl_generic(
         E: #export::Error
       {
         #export::Ok(match value {
-          #( #names_str => __Field::#field_enums, )*
-          _ => __Field::__ignore
+          #( #names_str => #ident::#field_enums, )*
+          _ => #unknown_str
         })
       }
 
@@ -198,109 +732,676 @@ fn impl_generic(
         E: #export::Error
       {
         #export::Ok(match value {
-          #( #names_bytes => __Field::#field_enums, )*
-          _ => __Field::__ignore
+          #( #names_bytes => #ident::#field_enums, )*
+          _ => #unknown_bytes
         })
       }
     }
-  } else {
-    quote! {}
+  } else {
+    quote! {}
+  };
+
+  let (ignore, unknown_u64) = match unknown {
+    UnknownFields::Ignore => (quote! { __ignore, __unknown(#export::String) }, None),
+    UnknownFields::Deny => (quote! {}, None),
+    UnknownFields::Collect => (
+      quote! { __other(#export::Content<'static>) },
+      Some(quote! { #ident::__other(#export::Content::U64(value)) }),
+    ),
+  };
+  let unknown_u64 = unknown_u64.unwrap_or_else(|| {
+    quote! {
+      return Err(#export::Error::invalid_value(
+        #export::Unexpected::Unsigned(value),
+        &#missing_field_error_str
+      ))
+    }
+  });
+
+  quote! {
+    #[allow(non_camel_case_types)]
+    enum #ident {
+      #( #field_enums, )*
+      #ignore
+    }
+    impl<'de> #export::Deserialize<'de> for #ident {
+      fn deserialize<D>(#deserializer: D) -> #export::Result<Self, D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        #export::Deserializer::deserialize_identifier(#deserializer, #visitor(false))
+      }
+    }
+
+    // Deserializes the identifier, keeping the names of unknown fields if the
+    // flag is set.
+    struct #visitor(bool);
+    impl<'de> #export::DeserializeSeed<'de> for #visitor {
+      type Value = #ident;
+
+      fn deserialize<D>(self, #deserializer: D) -> #export::Result<Self::Value, D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        #export::Deserializer::deserialize_identifier(#deserializer, self)
+      }
+    }
+    impl<'de> #export::Visitor<'de> for #visitor {
+      type Value = #ident;
+
+      fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
+        #export::fmt::Formatter::write_str(fmt, "field identifier")
+      }
+
+      fn visit_u64<E>(self, value: u64) -> #export::Result<Self::Value, E>
+      where
+        E: #export::Error
+      {
+        use #export::{Ok, Err};
+
+        Ok(match value {
+          #( #indices_u64 => #ident::#field_enums, )*
+          _ => #unknown_u64
+        })
+      }
+
+      #visit_str_and_bytes_impl
+    }
+  }
+}
+
+/// The names of the fields which are read from their own key, including
+/// aliases. A flattened struct claims the entries whose keys are in this list.
+fn fields_const_names(fields: &[FieldInfo]) -> Vec<syn::LitStr> {
+  fields
+    .iter()
+    .filter(|x| x.is_keyed())
+    .flat_map(|x| x.accepted_names())
+    .collect()
+}
+
+/// Build the `str` and byte string patterns which match any of `names`.
+fn name_patterns(names: &[syn::LitStr]) -> (TokenStream, TokenStream) {
+  let bytes = names
+    .iter()
+    .map(|x| syn::LitByteStr::new(x.value().as_bytes(), x.span()));
+
+  (quote! { #( #names )|* }, quote! { #( #bytes )|* })
+}
+
+/// Generate the body of a `visit_seq` method which deserializes over the
+/// fields at `places`.
+fn merge_seq_body(export: &syn::Path, fields: &[FieldInfo], places: &[TokenStream]) -> TokenStream {
+  let entries = fields
+    .iter()
+    .zip(places)
+    .filter(|(field, _)| !field.skip)
+    .map(|(field, place)| field.seq_de(export, place));
+
+  quote! { #( #entries; )* }
+}
+
+/// Generate the body of a `visit_map` method which deserializes over the
+/// fields at `places` using the field identifier enum `ident`.
+fn merge_map_body(
+  export: &syn::Path,
+  ident: &Ident,
+  fields: &[FieldInfo],
+  places: &[TokenStream],
+  unknown: UnknownFields,
+) -> TokenStream {
+  let flattened = fields
+    .iter()
+    .zip(places)
+    .filter(|(field, _)| field.flatten)
+    .map(|(field, place)| {
+      let wrapper = field.build_de_wrapper(export, place);
+      let deserialize = quote! {
+        #export::DeserializeSeed::deserialize(
+          #wrapper,
+          #export::FlatMapDeserializer::new(&mut __collect),
+        )?;
+      };
+
+      // A flattened field which is replaced is left alone unless the input
+      // has some of its keys, as it would otherwise be reset or fail.
+      match field.passthrough || field.deserialize_with.is_some() {
+        true => deserialize,
+        false => {
+          let ty = &field.ty;
+          quote! {
+            if #export::flat_entries_present::<#ty>(&__collect) {
+              #deserialize
+            }
+          }
+        }
+      }
+    })
+    .collect::<Vec<_>>();
+  let (fields, places): (Vec<_>, Vec<_>) = fields
+    .iter()
+    .zip(places)
+    .filter(|(field, _)| field.is_keyed())
+    .unzip();
+  let field_enums = fields.iter().map(|x| &x.enum_value).collect::<Vec<_>>();
+  let field_names = fields.iter().map(|x| x.source_name()).collect::<Vec<_>>();
+  let entries = fields
+    .iter()
+    .zip(places)
+    .map(|(field, place)| field.map_de(export, place));
+  let collect = collect_decl(export, unknown);
+  let unknown_arm = unknown_arm(export, ident, unknown, &field_names);
+  let visitor = Ident::new(&format!("{}Visitor", ident), Span::call_site());
+
+  quote! {
+    #collect
+
+    // State tracking
+    #(
+      let mut #field_enums: bool = false;
+    )*
+
+    while let Some(key) = map.next_key_seed(#visitor(__cx.is_tracking_unknown_keys()))? {
+      match key {
+        #(
+          #ident::#field_enums => if #field_enums {
+            return Err(<A::Error as Error>::duplicate_field(#field_names));
+          } else {
+            #field_enums = true;
+            #entries;
+          }
+        )*
+        #unknown_arm
+      }
+    }
+
+    #( #flattened )*
+  }
+}
+
+/// Generate the body of a `visit_seq` method which deserializes all `fields`
+/// into fresh values and then evaluates `construct` with them in scope.
+fn fresh_seq_body(
+  export: &syn::Path,
+  fields: &[FieldInfo],
+  expecting: &str,
+  construct: &TokenStream,
+) -> TokenStream {
+  let (fields, skipped): (Vec<_>, Vec<_>) = fields.iter().partition(|x| !x.skip);
+  let expecting = format!("{} with {} elements", expecting, fields.len());
+  let entries = fields.iter().enumerate().map(|(idx, field)| {
+    let var = &field.enum_value;
+    let seed = field.build_fresh_seed(export);
+
+    quote! {
+      let #var = match seq.next_element_seed(#seed)? {
+        Some(value) => value,
+        None => return Err(<A::Error as Error>::invalid_length(#idx, &#expecting)),
+      };
+    }
+  });
+  let skipped = skipped_values(export, &skipped);
+
+  quote! {
+    #( #entries )*
+    #skipped
+    #construct
+  }
+}
+
+/// Generate the body of a `visit_map` method which deserializes all `fields`
+/// into fresh values and then evaluates `construct` with them in scope.
+fn fresh_map_body(
+  export: &syn::Path,
+  ident: &Ident,
+  fields: &[FieldInfo],
+  construct: &TokenStream,
+  unknown: UnknownFields,
+) -> TokenStream {
+  let (fields, skipped): (Vec<_>, Vec<_>) = fields.iter().partition(|x| !x.skip);
+  let (fields, flattened): (Vec<_>, Vec<_>) = fields.into_iter().partition(|x| !x.flatten);
+  let field_enums = fields.iter().map(|x| &x.enum_value).collect::<Vec<_>>();
+  let field_names = fields.iter().map(|x| x.source_name()).collect::<Vec<_>>();
+  let field_tys = fields.iter().map(|x| &x.ty);
+  let seeds = fields.iter().map(|x| x.build_fresh_seed(export));
+  let missing = fields.iter().map(|x| x.missing_value(export));
+  let skipped = skipped_values(export, &skipped);
+  let flattened = flattened.iter().map(|field| {
+    let var = &field.enum_value;
+    let seed = field.build_fresh_seed(export);
+
+    quote! {
+      let #var = #export::DeserializeSeed::deserialize(
+        #seed,
+        #export::FlatMapDeserializer::new(&mut __collect),
+      )?;
+    }
+  });
+  let collect = collect_decl(export, unknown);
+  let unknown_arm = unknown_arm(export, ident, unknown, &field_names);
+  let visitor = Ident::new(&format!("{}Visitor", ident), Span::call_site());
+
+  quote! {
+    #collect
+    #(
+      let mut #field_enums: #export::Option<#field_tys> = None;
+    )*
+
+    while let Some(key) = map.next_key_seed(#visitor(__cx.is_tracking_unknown_keys()))? {
+      match key {
+        #(
+          #ident::#field_enums => if #field_enums.is_some() {
+            return Err(<A::Error as Error>::duplicate_field(#field_names));
+          } else {
+            #field_enums = Some(map.next_value_seed(#seeds)?);
+          }
+        )*
+        #unknown_arm
+      }
+    }
+
+    #( #flattened )*
+
+    #(
+      let #field_enums = match #field_enums {
+        Some(value) => value,
+        None => #missing,
+      };
+    )*
+    #skipped
+
+    #construct
+  }
+}
+
+/// Bind the default values of skipped fields when building a fresh instance.
+fn skipped_values(export: &syn::Path, skipped: &[&FieldInfo]) -> TokenStream {
+  let vars = skipped.iter().map(|x| &x.enum_value);
+  let values = skipped.iter().map(|x| x.missing_value(export));
+
+  quote! { #( let #vars = #values; )* }
+}
+
+/// Declare the buffer used to collect unknown entries for flattened fields.
+fn collect_decl(export: &syn::Path, unknown: UnknownFields) -> TokenStream {
+  match unknown {
+    UnknownFields::Collect => quote! {
+      let mut __collect =
+        #export::Vec::<#export::Option<(#export::Content<'de>, #export::Content<'de>)>>::new();
+    },
+    _ => quote! {},
+  }
+}
+
+/// The match arm which handles the value of an unknown field. Unknown fields
+/// are rejected by the field identifier when they are denied so no arm is
+/// needed in that case.
+///
+/// The identifier only keeps the name of an ignored field when the `Context`
+/// bound to `__cx` tracks unknown keys, in which case it is recorded there with
+/// `expected` as the known fields.
+fn unknown_arm(
+  export: &syn::Path,
+  ident: &Ident,
+  unknown: UnknownFields,
+  expected: &[syn::LitStr],
+) -> TokenStream {
+  match unknown {
+    UnknownFields::Ignore => quote! {
+      #ident::__ignore => {
+        map.next_value::<#export::IgnoredAny>()?;
+      }
+      #ident::__unknown(__name) => {
+        __cx.record_unknown_key(&__name, &[ #( #expected ),* ]);
+        map.next_value::<#export::IgnoredAny>()?;
+      }
+    },
+    UnknownFields::Deny => quote! {},
+    UnknownFields::Collect => quote! {
+      #ident::__other(__name) => {
+        __collect.push(Some((__name, map.next_value::<#export::Content<'de>>()?)));
+      }
+    },
+  }
+}
+
+/// Generic arguments to use when naming a type declared with `generics`.
+fn generic_args(generics: &Generics) -> Punctuated<TokenStream, Token![,]> {
+  generics
+    .params
+    .iter()
+    .map(|param| match param {
+      GenericParam::Type(ty) => ty.ident.to_token_stream(),
+      GenericParam::Lifetime(lt) => lt.lifetime.to_token_stream(),
+      GenericParam::Const(cnst) => cnst.ident.to_token_stream(),
+    })
+    .collect()
+}
+
+/// The `deserialize_over` method of a generated impl, which starts a new
+/// `Context` and forwards to `deserialize_over_with`.
+fn deserialize_over_fn(crate_name: &Ident, export: &syn::Path) -> TokenStream {
+  quote! {
+    fn deserialize_over<D>(&mut self, __deserializer: D) -> #export::Result<(), D::Error>
+    where
+      D: #export::Deserializer<'de>
+    {
+      #crate_name::DeserializeOver::deserialize_over_with(
+        self,
+        __deserializer,
+        &mut #export::Context::new(),
+      )
+    }
+  }
+}
+
+/// The `serialized_field_names` method of a generated impl, which maps the
+/// Rust name of each keyed field to the names it is deserialized from.
+fn serialized_field_names_fn(export: &syn::Path, fields: &[FieldInfo]) -> TokenStream {
+  let (members, names): (Vec<_>, Vec<_>) = fields
+    .iter()
+    .filter(|field| field.is_keyed())
+    .filter_map(|field| match &field.member {
+      Member::Named(ident) => Some((ident.unraw().to_string(), field.accepted_names())),
+      Member::Unnamed(_) => None,
+    })
+    .unzip();
+
+  quote! {
+    fn serialized_field_names(
+      __member: &str,
+    ) -> #export::Option<&'static [&'static str]> {
+      match __member {
+        #( #members => #export::Some(&[ #( #names ),* ]), )*
+        _ => #export::None,
+      }
+    }
+  }
+}
+
+/// Wrap the generated impl in an anonymous const so that the helper items it
+/// declares don't leak into the surrounding module.
+fn wrap_impl(real_crate_name: &Ident, inner: TokenStream) -> TokenStream {
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+
+  quote! {
+    #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, non_camel_case_types)]
+    const _: () = {
+      #[allow(unknown_lints)]
+      #[allow(rust_2018_idioms)]
+      extern crate #real_crate_name as #crate_name;
+
+      #inner
+    };
+  }
+}
+
+fn impl_generic(
+  mut input: DeriveInput,
+  real_crate_name: Ident,
+  attrs: ContainerAttr,
+  fields: Vec<FieldInfo>,
+  fields_numbered: bool,
+) -> syn::Result<TokenStream> {
+  let struct_name = &input.ident;
+  let name = attrs.name(struct_name);
+  let deserializer = Ident::new("__deserializer", Span::call_site());
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+  let export = syn::parse_quote! { #crate_name::export };
+  let deserialize_over = deserialize_over_fn(&crate_name, &export);
+  let serialized_field_names = serialized_field_names_fn(&export, &fields);
+  let field_ident = Ident::new("__Field", Span::call_site());
+  let unknown = UnknownFields::new(attrs.deny_unknown_fields, &fields)?;
+
+  let field_names = fields_const_names(&fields);
+  let places = fields
+    .iter()
+    .map(|field| {
+      let member = &field.member;
+      quote! { (self.0).#member }
+    })
+    .collect::<Vec<_>>();
+
+  let identifier = field_identifier(&export, &field_ident, &fields, fields_numbered, unknown);
+  let visit_seq_body = merge_seq_body(&export, &fields, &places);
+  let visit_map_body = merge_map_body(&export, &field_ident, &fields, &places, unknown);
+
+  // Flattened fields consume whatever keys are left over so the struct has to
+  // be deserialized as a map.
+  let (visit_seq, deserialize) = if unknown == UnknownFields::Collect {
+    (
+      quote! {},
+      quote! {
+        #export::Deserializer::deserialize_map(#deserializer, __Visitor(self, __cx))
+      },
+    )
+  } else {
+    (
+      quote! {
+        fn visit_seq<A>(self, mut seq: A) -> #export::Result<Self::Value, A::Error>
+        where
+          A: #export::SeqAccess<'de>
+        {
+          use #export::{Some, None};
+          let __cx = &mut *self.1;
+
+          #visit_seq_body
+
+          Ok(())
+        }
+      },
+      quote! {
+        const FIELDS: &[&str] = &[
+          #( #field_names, )*
+        ];
+
+        #export::Deserializer::deserialize_struct(
+          #deserializer,
+          #name,
+          FIELDS,
+          __Visitor(self, __cx)
+        )
+      },
+    )
+  };
+
+  // The visitor only needs the bounds required by the type itself.
+  let type_where_clause = input.generics.where_clause.clone();
+  if !input.generics.params.is_empty() {
+    let where_clause = input.generics.make_where_clause();
+
+    for field in fields.iter() {
+      field.add_bounds(where_clause, &crate_name, false);
+    }
+  }
+
+  let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+  let impl_generics = &input.generics.params;
+  let visitor_params = generic_args(&input.generics);
+
+  let inner = quote! {
+    #[automatically_derived]
+    impl<'de, #impl_generics> #crate_name::DeserializeOver<'de> for #struct_name #ty_generics
+      #where_clause
+    {
+      #deserialize_over
+
+      #serialized_field_names
+
+      fn deserialize_over_with<D>(
+        &mut self,
+        #deserializer: D,
+        __cx: &mut #export::Context,
+      ) -> #export::Result<(), D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        #identifier
+
+        struct __Visitor<'a, #impl_generics>(
+          pub &'a mut #struct_name #ty_generics,
+          pub &'a mut #export::Context,
+        ) #type_where_clause;
+
+        impl<'a, 'de, #impl_generics> #export::Visitor<'de> for __Visitor<'a, #visitor_params>
+          #where_clause
+        {
+          type Value = ();
+
+          fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
+            #export::fmt::Formatter::write_str(fmt, concat!("struct ", stringify!(#struct_name)))
+          }
+
+          #visit_seq
+
+          fn visit_map<A>(self, mut map: A) -> #export::Result<Self::Value, A::Error>
+          where
+            A: #export::MapAccess<'de>
+          {
+            use #export::{Some, None, Error};
+            let __cx = &mut *self.1;
+
+            #visit_map_body
+
+            Ok(())
+          }
+        }
+
+        #deserialize
+      }
+    }
+  };
+
+  Ok(wrap_impl(&real_crate_name, inner))
+}
+
+fn impl_named_fields(
+  input: DeriveInput,
+  crate_name: Ident,
+  attrs: ContainerAttr,
+  fields: FieldsNamed,
+) -> syn::Result<TokenStream> {
+  let fieldinfos = FieldInfo::from_fields(&fields.named, attrs.rename_all, attrs.on_null)?;
+  let patch = match &attrs.patch {
+    Some(options) => {
+      self::patch::impl_patch(&input, &crate_name, &attrs, options, &fields, &fieldinfos)?
+    }
+    None => quote! {},
+  };
+  let diff = match attrs.diff {
+    true => self::diff::impl_diff(&input, &crate_name, &attrs, &fields, &fieldinfos)?,
+    false => quote! {},
   };
+  let imp = impl_generic(input, crate_name, attrs, fieldinfos, false)?;
 
-  let map_de_entries = fields
-    .iter()
-    .map(|field| field.map_de(&export))
-    .collect::<Vec<_>>();
+  Ok(quote! {
+    #imp
+    #patch
+    #diff
+  })
+}
 
-  let visit_seq_entries = fields
+fn impl_unnamed_fields(
+  mut input: DeriveInput,
+  real_crate_name: Ident,
+  attrs: ContainerAttr,
+  fields: FieldsUnnamed,
+) -> syn::Result<TokenStream> {
+  let struct_name = &input.ident;
+  let name = attrs.name(struct_name);
+  let deserializer = Ident::new("__deserializer", Span::call_site());
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+  let export = syn::parse_quote! { #crate_name::export };
+  let deserialize_over = deserialize_over_fn(&crate_name, &export);
+
+  let fields = FieldInfo::from_fields(&fields.unnamed, RenameRule::None, None)?;
+  if let Some(field) = fields.iter().find(|x| x.flatten) {
+    return Err(syn::Error::new(
+      field.span(),
+      "#[serde(flatten)] can only be used on named fields",
+    ));
+  }
+  let places = fields
     .iter()
-    .map(|field| field.seq_de(&export))
+    .map(|field| {
+      let member = &field.member;
+      quote! { (self.0).#member }
+    })
     .collect::<Vec<_>>();
+  let visit_seq_body = merge_seq_body(&export, &fields, &places);
 
+  // The visitor only needs the bounds required by the type itself.
+  let type_where_clause = input.generics.where_clause.clone();
   if !input.generics.params.is_empty() {
     let where_clause = input.generics.make_where_clause();
 
     for field in fields.iter() {
-      let ty = &field.ty;
-
-      if field.passthrough {
-        where_clause.predicates.push(parse_quote! {
-          #ty: #crate_name::DeserializeOver<'de>
-        });
-      } else {
-        where_clause.predicates.push(parse_quote! {
-          #ty: #crate_name::export::Deserialize<'de>
-        });
-      }
+      field.add_bounds(where_clause, &crate_name, false);
     }
   }
 
   let (_, ty_generics, where_clause) = input.generics.split_for_impl();
   let impl_generics = &input.generics.params;
+  let visitor_params = generic_args(&input.generics);
 
-  let visitor_params = impl_generics
-    .iter()
-    .map(|param| match param {
-      GenericParam::Type(ty) => ty.ident.to_token_stream(),
-      GenericParam::Lifetime(lt) => lt.lifetime.to_token_stream(),
-      GenericParam::Const(cnst) => cnst.ident.to_token_stream(),
-    })
-    .collect::<Punctuated<_, Token![,]>>();
+  // Newtype structs forward directly to their inner field while everything
+  // else is deserialized as a sequence.
+  let (visit_newtype, deserialize) = if fields.len() == 1 && !fields[0].skip {
+    let wrapper = fields[0].build_de_wrapper(&export, &places[0]);
 
-  let inner = quote! {
-    #[allow(unknown_lints)]
-    #[allow(rust_2018_idioms)]
-    extern crate #real_crate_name as #crate_name;
+    (
+      quote! {
+        fn visit_newtype_struct<__E>(self, __e: __E) -> #export::Result<Self::Value, __E::Error>
+        where
+          __E: #export::Deserializer<'de>
+        {
+          let __cx = &mut *self.1;
+          #export::DeserializeSeed::deserialize(#wrapper, __e)
+        }
+      },
+      quote! {
+        #export::Deserializer::deserialize_newtype_struct(
+          #deserializer,
+          #name,
+          __Visitor(self, __cx)
+        )
+      },
+    )
+  } else {
+    let len = fields.iter().filter(|x| !x.skip).count();
+
+    (
+      quote! {},
+      quote! {
+        #export::Deserializer::deserialize_tuple_struct(
+          #deserializer,
+          #name,
+          #len,
+          __Visitor(self, __cx)
+        )
+      },
+    )
+  };
 
+  let inner = quote! {
     #[automatically_derived]
     impl<'de, #impl_generics> #crate_name::DeserializeOver<'de> for #struct_name #ty_generics
       #where_clause
     {
-      fn deserialize_over<D>(&mut self, #deserializer: D) -> #export::Result<(), D::Error>
+      #deserialize_over
+
+      fn deserialize_over_with<D>(
+        &mut self,
+        #deserializer: D,
+        __cx: &mut #export::Context,
+      ) -> #export::Result<(), D::Error>
       where
         D: #export::Deserializer<'de>
       {
-        #[allow(non_camel_case_types)]
-        enum __Field {
-          #( #field_enums, )*
-          __ignore
-        }
-        impl<'de> #export::Deserialize<'de> for __Field {
-          fn deserialize<D>(#deserializer: D) -> #export::Result<Self, D::Error>
-          where
-            D: #export::Deserializer<'de>
-          {
-            #export::Deserializer::deserialize_identifier(#deserializer, __FieldVisitor)
-          }
-        }
-
-        struct __FieldVisitor;
-        impl<'de> #export::Visitor<'de> for __FieldVisitor {
-          type Value = __Field;
-
-          fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
-            #export::fmt::Formatter::write_str(fmt, "field identifier")
-          }
-
-          fn visit_u64<E>(self, value: u64) -> #export::Result<Self::Value, E>
-          where
-            E: #export::Error
-          {
-            use #export::{Ok, Err};
-
-            Ok(match value {
-              #( #indices_u64 => __Field::#field_enums, )*
-              _ => return Err(#export::Error::invalid_value(
-                #export::Unexpected::Unsigned(value),
-                &#missing_field_error_str
-              ))
-            })
-          }
-
-          #visit_str_and_bytes_impl
-        }
-
-        struct __Visitor<'a, #impl_generics>(pub &'a mut #struct_name #ty_generics);
+        struct __Visitor<'a, #impl_generics>(
+          pub &'a mut #struct_name #ty_generics,
+          pub &'a mut #export::Context,
+        ) #type_where_clause;
 
         impl<'a, 'de, #impl_generics> #export::Visitor<'de> for __Visitor<'a, #visitor_params>
           #where_clause
@@ -308,161 +1409,225 @@ fn impl_generic(
           type Value = ();
 
           fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
-            #export::fmt::Formatter::write_str(fmt, concat!("struct ", stringify!(#struct_name)))
+            #export::fmt::Formatter::write_str(fmt, concat!("tuple struct ", stringify!(#struct_name)))
           }
 
+          #visit_newtype
+
           fn visit_seq<A>(self, mut seq: A) -> #export::Result<Self::Value, A::Error>
           where
             A: #export::SeqAccess<'de>
           {
             use #export::{Some, None};
+            let __cx = &mut *self.1;
 
-            #( #visit_seq_entries; )*
-
-            Ok(())
-          }
-
-          fn visit_map<A>(self, mut map: A) -> #export::Result<Self::Value, A::Error>
-          where
-            A: #export::MapAccess<'de>
-          {
-            use #export::{Some, None, Error};
-
-            // State tracking
-            #(
-              let mut #field_enums: bool = false;
-            )*
-
-            while let Some(key) = map.next_key::<__Field>()? {
-              match key {
-                #(
-                  __Field::#field_enums => if #field_enums_copy1 {
-                    return Err(<A::Error as Error>::duplicate_field(stringify!(#field_names)));
-                  } else {
-                    #field_enums_copy2 = true;
-                    #map_de_entries;
-                  }
-                )*
-                _ => (),
-              }
-            }
+            #visit_seq_body
 
             Ok(())
           }
         }
 
-        const FIELDS: &[&str] = &[
-          #( stringify!(#field_names), )*
-        ];
-
-        #export::Deserializer::deserialize_struct(
-          #deserializer,
-          stringify!(#struct_name),
-          FIELDS,
-          __Visitor(self)
-        )
+        #deserialize
       }
     }
   };
 
-  let const_name = Ident::new(
-    &format!("_IMPL_DESERIALIZE_OVER_FOR_{}", struct_name),
-    struct_name.span(),
-  );
-
-  Ok(
-    quote! {
-      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, non_camel_case_types)]
-      const #const_name: () = {
-        #inner
-      };
-    }
-    .into(),
-  )
+  Ok(wrap_impl(&real_crate_name, inner))
 }
 
-fn impl_named_fields(
+fn impl_unit(
   input: DeriveInput,
-  crate_name: Ident,
-  fields: FieldsNamed,
+  real_crate_name: Ident,
+  attrs: ContainerAttr,
 ) -> syn::Result<TokenStream> {
-  let fieldinfos = fields
-    .named
-    .iter()
-    .enumerate()
-    .map(|(idx, x)| {
-      let attr = parse_attr(x.attrs.iter())?;
+  let struct_name = &input.ident;
+  let name = attrs.name(struct_name);
+  let deserializer = Ident::new("__deserializer", Span::call_site());
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+  let export: syn::Path = syn::parse_quote! { #crate_name::export };
+  let deserialize_over = deserialize_over_fn(&crate_name, &export);
 
-      let name = x.ident.clone().unwrap();
+  let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+  let impl_generics = &input.generics.params;
 
-      Ok(FieldInfo {
-        enum_value: Ident::new(&format!("__field{}", idx), name.span()),
+  let inner = quote! {
+    #[automatically_derived]
+    impl<'de, #impl_generics> #crate_name::DeserializeOver<'de> for #struct_name #ty_generics
+      #where_clause
+    {
+      #deserialize_over
 
-        name,
-        ty: x.ty.clone(),
-        passthrough: attr.use_deserialize_over,
-        deserialize_with: attr.deserialize_fn,
-        deserialize_merge_with: attr.deserialize_merge_fn,
-        srcname: attr.rename.map(|x| x.value()),
-      })
-    })
-    .collect::<Result<Vec<_>, syn::Error>>()?;
+      fn deserialize_over_with<D>(
+        &mut self,
+        #deserializer: D,
+        __cx: &mut #export::Context,
+      ) -> #export::Result<(), D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        struct __Visitor;
 
-  return impl_generic(input, crate_name, fieldinfos, false);
-}
+        impl<'de> #export::Visitor<'de> for __Visitor {
+          type Value = ();
 
-fn impl_unnamed_fields(
-  _input: DeriveInput,
-  _crate_name: Ident,
-  _fields: FieldsUnnamed,
-) -> syn::Result<TokenStream> {
-  panic!("Deriving DeserializeInto for tuple structs is not supported");
-}
+          fn expecting(&self, fmt: &mut #export::fmt::Formatter) -> #export::fmt::Result {
+            #export::fmt::Formatter::write_str(fmt, concat!("unit struct ", stringify!(#struct_name)))
+          }
 
-fn impl_unit(input: DeriveInput, crate_name: Ident) -> syn::Result<TokenStream> {
-  let struct_name = &input.ident;
+          fn visit_unit<E>(self) -> #export::Result<Self::Value, E>
+          where
+            E: #export::Error
+          {
+            #export::Ok(())
+          }
 
-  Ok(
-    quote! {
-      impl ::#crate_name::DeserializeOver for #struct_name {
-        fn deserialize_over<'de, D>(&mut self, de: D) -> Result<(), D::Error>
-        where
-          D: Deserializer<'de>
-        {
-          Ok(())
+          fn visit_map<A>(self, mut map: A) -> #export::Result<Self::Value, A::Error>
+          where
+            A: #export::MapAccess<'de>
+          {
+            match map.next_key::<#export::IgnoredAny>()? {
+              #export::Some(_) => #export::Err(#export::Error::invalid_length(1, &self)),
+              #export::None => #export::Ok(()),
+            }
+          }
         }
+
+        #export::Deserializer::deserialize_unit_struct(
+          #deserializer,
+          #name,
+          __Visitor
+        )
       }
     }
-    .into(),
-  )
+  };
+
+  Ok(wrap_impl(&real_crate_name, inner))
 }
 
 #[derive(Default)]
 struct ParsedAttr {
   use_deserialize_over: bool,
+  vec: Option<(VecStrategy, Span)>,
+  set: Option<SetStrategy>,
+  merge_by: Option<MergeBy>,
+  map: Option<MapOptions>,
+  on_null: Option<(OnNull, Span)>,
   deserialize_fn: Option<Path>,
   deserialize_merge_fn: Option<Path>,
+  default: Option<FieldDefault>,
   rename: Option<syn::LitStr>,
+  aliases: Vec<syn::LitStr>,
+  skip: bool,
+  flatten: bool,
 }
 
 fn parse_attr<'a, I>(attrs: I) -> syn::Result<ParsedAttr>
 where
   I: Iterator<Item = &'a Attribute>,
 {
-  use syn::spanned::Spanned;
-
   let mut result = ParsedAttr::default();
 
   for attr in attrs.into_iter() {
     if attr.path.is_ident("deserialize_over") {
-      if !attr.tokens.is_empty() {
+      result.use_deserialize_over = true;
+
+      if attr.tokens.is_empty() {
+        continue;
+      }
+
+      let body: self::attr::SerdeAttrBody = syn::parse2(attr.tokens.clone())?;
+      let mut seen = HashSet::new();
+      let mut tombstone = None;
+      let mut delete_null = false;
+      let mut replace_values = false;
+
+      for opt in body.attrs.iter() {
+        if !seen.insert(opt.ident().to_string()) {
+          return Err(syn::Error::new_spanned(
+            opt,
+            format!(
+              "Option `{}` cannot be specified multiple times",
+              opt.ident()
+            ),
+          ));
+        }
+
+        match opt {
+          SerdeOption::String(opt) if opt.ident == "vec" => {
+            result.vec = Some((VecStrategy::from_lit(&opt.value)?, opt.span()));
+          }
+          SerdeOption::String(opt) if opt.ident == "set" => {
+            result.set = Some(SetStrategy::from_lit(&opt.value)?);
+          }
+          SerdeOption::String(opt) if opt.ident == "merge_by" => {
+            result.merge_by = Some(MergeBy {
+              key: opt.value.clone(),
+              member: opt
+                .value
+                .parse()
+                .map_err(|_| syn::Error::new_spanned(&opt.value, "expected a field name"))?,
+              tombstone: None,
+            });
+          }
+          SerdeOption::String(opt) if opt.ident == "on_null" => {
+            result.on_null = Some((OnNull::from_lit(&opt.value)?, opt.span()));
+          }
+          SerdeOption::String(opt) if opt.ident == "tombstone" => {
+            tombstone = Some(opt.value.clone());
+          }
+          SerdeOption::Flag(flag) if flag == "delete_null" => delete_null = true,
+          SerdeOption::Flag(flag) if flag == "replace_values" => replace_values = true,
+          opt => {
+            return Err(syn::Error::new_spanned(
+              opt,
+              format!(
+                "#[deserialize_over({})] is not a supported option",
+                opt.ident()
+              ),
+            ))
+          }
+        }
+      }
+
+      // Without `merge_by` a tombstone refers to the values of a map.
+      match (&mut result.merge_by, delete_null) {
+        (Some(_), true) => {
+          return Err(syn::Error::new(
+            body.span_for("delete_null"),
+            "Cannot specify both `merge_by` and `delete_null`",
+          ))
+        }
+        (Some(merge_by), false) => merge_by.tombstone = tombstone,
+        (None, _) if delete_null || replace_values || tombstone.is_some() => {
+          result.map = Some(MapOptions {
+            delete_null,
+            tombstone,
+            replace_values,
+          })
+        }
+        (None, _) => (),
+      }
+
+      if result.merge_by.is_some() && replace_values {
+        return Err(syn::Error::new(
+          body.span_for("replace_values"),
+          "Cannot specify both `merge_by` and `replace_values`",
+        ));
+      }
+      let kinds = [
+        result.vec.is_some(),
+        result.set.is_some(),
+        result.merge_by.is_some(),
+        result.map.is_some(),
+      ];
+      if kinds.iter().filter(|&&x| x).count() > 1 {
         return Err(syn::Error::new_spanned(
-          attr.path.to_token_stream(),
-          "deserialize_over attribute should not have any arguments",
+          &body.attrs,
+          "Options for different kinds of collections cannot be combined. Use only one of \
+           `vec`, `set`, `merge_by` or the map options `tombstone`, `delete_null` and \
+           `replace_values`",
         ));
       }
-
-      result.use_deserialize_over = true;
     } else if attr.path.is_ident("serde") {
       let body: self::attr::SerdeAttrBody = syn::parse2(attr.tokens.clone())?;
       let mut seen = HashSet::new();
@@ -475,12 +1640,17 @@ where
         match &*ident {
           "with" | "deserialize_with" | "serialize_with" => (),
           "rename" | "serialize" | "deserialize" => (),
-          // #[serde(default)] is ignored since we already have values for all fields
+          // Aliases can be given multiple times.
+          "alias" => continue,
+          // #[serde(default)] is only used when a fresh value needs to be
+          // created since otherwise we already have values for all fields.
           "default" => (),
+          "skip" | "skip_deserializing" => (),
+          "flatten" => (),
           name => {
             return Err(syn::Error::new(
               opt.span(),
-              &format!(
+              format!(
                 r#"#[serde({}{}) is not supported by the DeserializeOver derive macro."#,
                 name,
                 if opt.is_flag() { r#" = "...""# } else { "" }
@@ -492,7 +1662,7 @@ where
         if !seen.insert(ident) {
           return Err(syn::Error::new_spanned(
             opt,
-            &format!(
+            format!(
               "Option `{}` cannot be specified multiple times",
               opt.ident()
             ),
@@ -523,6 +1693,14 @@ where
           Some(syn::parse_str(&lit.value()).map_err(|e| syn::Error::new_spanned(lit, e))?);
       }
 
+      if body.has("default") {
+        result.default = Some(FieldDefault::Default);
+      } else if let Some(lit) = body.get("default") {
+        result.default = Some(FieldDefault::Path(
+          syn::parse_str(&lit.value()).map_err(|e| syn::Error::new_spanned(lit, e))?,
+        ));
+      }
+
       if let Some(lit) = body.get("rename") {
         result.rename = Some(lit.clone());
       }
@@ -537,7 +1715,246 @@ where
 
         result.rename = Some(lit.clone());
       }
+
+      result.aliases.extend(body.get_all("alias").cloned());
+
+      if body.has("skip") || body.has("skip_deserializing") {
+        result.skip = true;
+      }
+
+      if body.has("flatten") {
+        result.flatten = true;
+      }
+    }
+  }
+
+  if let (Some((_, span)), Some(_)) = (result.vec, &result.deserialize_fn) {
+    return Err(syn::Error::new(
+      span,
+      "#[deserialize_over(vec = \"...\")] cannot be combined with #[serde(with)] or \
+       #[serde(deserialize_with)]",
+    ));
+  }
+  if let (Some(_), Some(path)) = (result.set, &result.deserialize_fn) {
+    return Err(syn::Error::new_spanned(
+      path,
+      "#[deserialize_over(set = \"...\")] cannot be combined with #[serde(with)] or \
+       #[serde(deserialize_with)]",
+    ));
+  }
+  if let (Some(merge_by), Some(_)) = (&result.merge_by, &result.deserialize_fn) {
+    return Err(syn::Error::new_spanned(
+      &merge_by.key,
+      "#[deserialize_over(merge_by = \"...\")] cannot be combined with #[serde(with)] or \
+       #[serde(deserialize_with)]",
+    ));
+  }
+  if let (Some(_), Some(path)) = (&result.map, &result.deserialize_fn) {
+    return Err(syn::Error::new_spanned(
+      path,
+      "#[deserialize_over(tombstone)], #[deserialize_over(delete_null)] and \
+       #[deserialize_over(replace_values)] cannot be combined with #[serde(with)] or \
+       #[serde(deserialize_with)]",
+    ));
+  }
+
+  Ok(result)
+}
+
+#[derive(Default)]
+struct ContainerAttr {
+  rename: Option<syn::LitStr>,
+  rename_all: RenameRule,
+  rename_all_fields: RenameRule,
+  deny_unknown_fields: bool,
+  tag: Option<syn::LitStr>,
+  content: Option<syn::LitStr>,
+  untagged: bool,
+  on_null: Option<OnNull>,
+  patch: Option<PatchOptions>,
+  diff: bool,
+}
+
+/// Options for the typed patch generated by `#[deserialize_over(patch)]`.
+#[derive(Default)]
+struct PatchOptions {
+  /// The name of the patch type, if not the default.
+  name: Option<Ident>,
+  /// Extra traits to derive for the patch type.
+  derives: Vec<Path>,
+}
+
+impl ContainerAttr {
+  /// The name of the container as passed to the deserializer.
+  fn name(&self, ident: &Ident) -> syn::LitStr {
+    match &self.rename {
+      Some(name) => name.clone(),
+      None => syn::LitStr::new(&ident.unraw().to_string(), ident.span()),
+    }
+  }
+}
+
+fn parse_container_attr(input: &DeriveInput) -> syn::Result<ContainerAttr> {
+  let is_enum = matches!(input.data, Data::Enum(_));
+  let mut result = ContainerAttr::default();
+  let mut seen = HashSet::new();
+  let mut patch_derive = None;
+
+  for attr in input.attrs.iter() {
+    if attr.path.is_ident("deserialize_over") {
+      let body: self::attr::SerdeAttrBody = syn::parse2(attr.tokens.clone())?;
+
+      for opt in body.attrs.iter() {
+        match opt {
+          SerdeOption::String(opt) if opt.ident == "on_null" && result.on_null.is_none() => {
+            result.on_null = Some(OnNull::from_lit(&opt.value)?);
+          }
+          SerdeOption::Flag(flag) if flag == "patch" && result.patch.is_none() => {
+            result.patch = Some(PatchOptions::default());
+          }
+          SerdeOption::String(opt) if opt.ident == "patch" && result.patch.is_none() => {
+            result.patch = Some(PatchOptions {
+              name: Some(opt.value.parse()?),
+              ..Default::default()
+            });
+          }
+          SerdeOption::String(opt) if opt.ident == "patch_derive" && patch_derive.is_none() => {
+            patch_derive = Some(opt.value.clone());
+          }
+          SerdeOption::Flag(flag) if flag == "diff" && !result.diff => {
+            result.diff = true;
+          }
+          opt => {
+            return Err(syn::Error::new_spanned(
+              opt,
+              format!(
+                "#[deserialize_over({})] is not supported on containers",
+                opt.ident()
+              ),
+            ))
+          }
+        }
+      }
+
+      continue;
+    }
+
+    if !attr.path.is_ident("serde") {
+      continue;
+    }
+
+    let body: self::attr::SerdeAttrBody = syn::parse2(attr.tokens.clone())?;
+
+    for opt in body.attrs.iter() {
+      let ident = opt.ident().to_string();
+
+      match &*ident {
+        "rename" | "rename_all" | "deny_unknown_fields" => (),
+        // Only relevant when serializing.
+        "into" => (),
+        // We never need to create a fresh instance of the container itself.
+        "default" if !is_enum => (),
+        "tag" | "content" | "untagged" | "rename_all_fields" if is_enum => (),
+        name => {
+          return Err(syn::Error::new(
+            opt.span(),
+            format!(
+              r#"#[serde({}{})] is not supported on {} by the DeserializeOver derive macro."#,
+              name,
+              if opt.is_flag() { "" } else { r#" = "...""# },
+              if is_enum { "enums" } else { "structs" }
+            ),
+          ))
+        }
+      }
+
+      if !seen.insert(ident) {
+        return Err(syn::Error::new_spanned(
+          opt,
+          format!(
+            "Option `{}` cannot be specified multiple times",
+            opt.ident()
+          ),
+        ));
+      }
+    }
+
+    if let Some(lit) = body.get("rename") {
+      result.rename = Some(lit.clone());
+    }
+
+    if let Some(lit) = body.get("rename_all") {
+      result.rename_all = RenameRule::from_lit(lit)?;
+    }
+
+    if let Some(lit) = body.get("rename_all_fields") {
+      result.rename_all_fields = RenameRule::from_lit(lit)?;
+    }
+
+    if body.has("deny_unknown_fields") {
+      result.deny_unknown_fields = true;
+    }
+
+    if let Some(lit) = body.get("tag") {
+      result.tag = Some(lit.clone());
+    }
+
+    if let Some(lit) = body.get("content") {
+      result.content = Some(lit.clone());
+    }
+
+    if body.has("untagged") {
+      result.untagged = true;
+    }
+  }
+
+  match (&result.tag, &result.content) {
+    (None, Some(content)) => {
+      return Err(syn::Error::new_spanned(
+        content,
+        "#[serde(content = \"...\")] cannot be used without #[serde(tag = \"...\")]",
+      ))
+    }
+    (Some(tag), _) if result.untagged => {
+      return Err(syn::Error::new_spanned(
+        tag,
+        "enum cannot be both untagged and tagged",
+      ))
+    }
+    _ => (),
+  }
+
+  let named = match &input.data {
+    Data::Struct(data) => matches!(data.fields, Fields::Named(_)),
+    _ => false,
+  };
+  if result.diff && !named {
+    return Err(syn::Error::new_spanned(
+      &input.ident,
+      "#[deserialize_over(diff)] is only supported on structs with named fields",
+    ));
+  }
+
+  match (&mut result.patch, patch_derive) {
+    (Some(_), _) if !named => {
+      return Err(syn::Error::new_spanned(
+        &input.ident,
+        "#[deserialize_over(patch)] is only supported on structs with named fields",
+      ))
+    }
+    (Some(patch), Some(lit)) => {
+      patch.derives = lit
+        .parse_with(Punctuated::<Path, Token![,]>::parse_terminated)?
+        .into_iter()
+        .collect();
+    }
+    (None, Some(lit)) => {
+      return Err(syn::Error::new_spanned(
+        lit,
+        "#[deserialize_over(patch_derive = \"...\")] requires #[deserialize_over(patch)]",
+      ))
     }
+    _ => (),
   }
 
   Ok(result)
@@ -0,0 +1,236 @@
+//! Generation of the typed patch struct for `#[deserialize_over(patch)]`.
+//!
+//! The patch struct gets its `Deserialize` impl by deriving `DeserializeOver`
+//! for it and deserializing over an empty patch, so that the field names and
+//! nested patches are handled the same way as for the original struct.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{ext::IdentExt, parse_quote, DeriveInput, FieldsNamed, Ident, PathArguments, Type};
+
+use crate::{option_inner, wrap_impl, ContainerAttr, FieldInfo, PatchOptions, CRATE_NAME};
+
+/// Whether the patch for `field` is a patch of its own type rather than a
+/// replacement value.
+///
+/// This is decided syntactically: only fields which are merged and whose type
+/// is a path without generic arguments, such as another struct, are nested.
+fn is_nested(field: &FieldInfo) -> bool {
+  let options = field.vec.is_some() || field.set.is_some() || field.map.is_some();
+  if !field.merges() || options || field.merge_by.is_some() {
+    return false;
+  }
+
+  match &field.ty {
+    Type::Path(ty) if ty.qself.is_none() => match ty.path.segments.last() {
+      Some(segment) => matches!(segment.arguments, PathArguments::None),
+      None => false,
+    },
+    _ => false,
+  }
+}
+
+pub(crate) fn impl_patch(
+  input: &DeriveInput,
+  real_crate_name: &Ident,
+  attrs: &ContainerAttr,
+  options: &PatchOptions,
+  fields: &FieldsNamed,
+  infos: &[FieldInfo],
+) -> syn::Result<TokenStream> {
+  let crate_name = Ident::new(&("_".to_owned() + CRATE_NAME), Span::call_site());
+  let export: syn::Path = parse_quote! { #crate_name::export };
+  let struct_name = &input.ident;
+  let vis = &input.vis;
+  let patch_name = match &options.name {
+    Some(name) => name.clone(),
+    None => Ident::new(&format!("{}Patch", struct_name.unraw()), struct_name.span()),
+  };
+  let patch_str = patch_name.unraw().to_string();
+  let derives = &options.derives;
+  let doc = format!(
+    " A typed patch for [`{}`]. Fields which are `None` are left unchanged.",
+    struct_name.unraw()
+  );
+
+  let mut generics = input.generics.clone();
+  let mut defs = Vec::new();
+  let mut idents = Vec::new();
+  let mut names = Vec::new();
+  let mut patch_tys = Vec::new();
+  let mut applies = Vec::new();
+
+  for (field, info) in fields.named.iter().zip(infos) {
+    if info.skip {
+      continue;
+    }
+    if info.flatten {
+      return Err(syn::Error::new(
+        info.span(),
+        "#[deserialize_over(patch)] does not support #[serde(flatten)] fields",
+      ));
+    }
+    if info.deserialize_with.is_some() {
+      return Err(syn::Error::new(
+        info.span(),
+        "#[deserialize_over(patch)] does not support fields with #[serde(deserialize_with)]",
+      ));
+    }
+
+    let ident = &field.ident;
+    let field_vis = &field.vis;
+    let ty = &info.ty;
+    let name = info.source_name();
+    let aliases = &info.aliases;
+
+    let (patch_ty, marker, apply) = match is_nested(info) {
+      true => {
+        if !input.generics.params.is_empty() {
+          let where_clause = generics.make_where_clause();
+          where_clause
+            .predicates
+            .push(parse_quote! { #ty: #real_crate_name::Patchable });
+        }
+
+        (
+          quote! { <#ty as #real_crate_name::Patchable>::Patch },
+          quote! { #[deserialize_over] },
+          quote! { #crate_name::Patch::apply(value, &mut target.#ident); },
+        )
+      }
+      // An explicit null has to become `Some(None)` so that the patch can
+      // clear the field.
+      false if option_inner(ty).is_some() => {
+        let with = format!("{}::export::deserialize_some", real_crate_name);
+        (
+          quote! { #ty },
+          quote! { #[serde(deserialize_with = #with)] },
+          quote! { target.#ident = value; },
+        )
+      }
+      false => (quote! { #ty }, quote! {}, quote! { target.#ident = value; }),
+    };
+
+    defs.push(quote! {
+      #[serde(rename = #name #( , alias = #aliases )*)]
+      #marker
+      #field_vis #ident: ::std::option::Option<#patch_ty>,
+    });
+    applies.push(quote! {
+      if let #export::Some(value) = self.#ident {
+        #apply
+      }
+    });
+    idents.push(ident);
+    names.push(name);
+    patch_tys.push(patch_ty);
+  }
+
+  let deny_unknown_fields = match attrs.deny_unknown_fields {
+    true => quote! { #[serde(deny_unknown_fields)] },
+    false => quote! {},
+  };
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+  let params = &generics.params;
+
+  let mut de_generics = generics.clone();
+  de_generics
+    .make_where_clause()
+    .predicates
+    .push(parse_quote! { #patch_name #ty_generics: #crate_name::DeserializeOver<'de> });
+  let de_where_clause = &de_generics.where_clause;
+
+  let mut ser_generics = generics.clone();
+  if !generics.params.is_empty() {
+    let where_clause = ser_generics.make_where_clause();
+    for ty in patch_tys.iter() {
+      where_clause
+        .predicates
+        .push(parse_quote! { #ty: #export::Serialize });
+    }
+  }
+  let ser_where_clause = &ser_generics.where_clause;
+
+  let inner = quote! {
+    #[automatically_derived]
+    impl #impl_generics #export::Default for #patch_name #ty_generics #where_clause {
+      fn default() -> Self {
+        Self {
+          #( #idents: #export::None, )*
+        }
+      }
+    }
+
+    impl #impl_generics #patch_name #ty_generics #where_clause {
+      /// Apply the fields which are set in this patch to `target`.
+      pub fn apply(self, target: &mut #struct_name #ty_generics) {
+        #( #applies )*
+      }
+    }
+
+    #[automatically_derived]
+    impl #impl_generics #crate_name::Patch<#struct_name #ty_generics>
+      for #patch_name #ty_generics
+      #where_clause
+    {
+      fn apply(self, target: &mut #struct_name #ty_generics) {
+        #patch_name::apply(self, target)
+      }
+    }
+
+    #[automatically_derived]
+    impl #impl_generics #crate_name::Patchable for #struct_name #ty_generics #where_clause {
+      type Patch = #patch_name #ty_generics;
+    }
+
+    #[automatically_derived]
+    impl<'de, #params> #export::Deserialize<'de> for #patch_name #ty_generics #de_where_clause {
+      fn deserialize<D>(__deserializer: D) -> #export::Result<Self, D::Error>
+      where
+        D: #export::Deserializer<'de>
+      {
+        let mut patch = <Self as #export::Default>::default();
+        #crate_name::DeserializeOver::deserialize_over(&mut patch, __deserializer)?;
+        #export::Ok(patch)
+      }
+    }
+
+    #[automatically_derived]
+    impl #impl_generics #export::Serialize for #patch_name #ty_generics #ser_where_clause {
+      fn serialize<S>(&self, __serializer: S) -> #export::Result<S::Ok, S::Error>
+      where
+        S: #export::Serializer
+      {
+        let mut __len = 0;
+        #(
+          if self.#idents.is_some() {
+            __len += 1;
+          }
+        )*
+
+        let mut __state = #export::Serializer::serialize_struct(__serializer, #patch_str, __len)?;
+        #(
+          match &self.#idents {
+            #export::Some(value) => {
+              #export::SerializeStruct::serialize_field(&mut __state, #names, value)?
+            }
+            #export::None => #export::SerializeStruct::skip_field(&mut __state, #names)?,
+          }
+        )*
+        #export::SerializeStruct::end(__state)
+      }
+    }
+  };
+  let imp = wrap_impl(real_crate_name, inner);
+
+  Ok(quote! {
+    #[doc = #doc]
+    #[derive(#real_crate_name::DeserializeOver #( , #derives )*)]
+    #deny_unknown_fields
+    #vis struct #patch_name #generics #where_clause {
+      #( #defs )*
+    }
+
+    #imp
+  })
+}
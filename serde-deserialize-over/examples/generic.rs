@@ -6,6 +6,7 @@ struct ExampleStruct<T> {
   pub b: i32,
 }
 
+#[allow(dead_code)]
 #[derive(DeserializeOver)]
 struct WithConstraints<T: Default> {
   pub a: T,
@@ -0,0 +1,918 @@
+//! Buffering of arbitrary deserializer input.
+//!
+//! Internally tagged, adjacently tagged and untagged enums need to look at the
+//! input before they know how to deserialize it. This module provides a
+//! [`Content`] type that can hold any self-describing input along with a
+//! deserializer that replays it. It is modelled after the private `Content`
+//! type used by serde's own derive macros.
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+  Deserialize, DeserializeSeed, Deserializer, EnumAccess, Error, IntoDeserializer, MapAccess,
+  SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A buffered value from a self-describing deserializer.
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub enum Content<'de> {
+  Bool(bool),
+
+  U8(u8),
+  U16(u16),
+  U32(u32),
+  U64(u64),
+
+  I8(i8),
+  I16(i16),
+  I32(i32),
+  I64(i64),
+
+  F32(f32),
+  F64(f64),
+
+  Char(char),
+  String(String),
+  Str(&'de str),
+  ByteBuf(Vec<u8>),
+  Bytes(&'de [u8]),
+
+  None,
+  Some(Box<Content<'de>>),
+
+  Unit,
+  Newtype(Box<Content<'de>>),
+  Seq(Vec<Content<'de>>),
+  Map(Vec<(Content<'de>, Content<'de>)>),
+}
+
+impl<'de> Content<'de> {
+  /// The string value of this content, if it is a string.
+  pub fn as_str(&self) -> Option<&str> {
+    match *self {
+      Content::Str(x) => Some(x),
+      Content::String(ref x) => Some(x),
+      Content::Bytes(x) => std::str::from_utf8(x).ok(),
+      Content::ByteBuf(ref x) => std::str::from_utf8(x).ok(),
+      _ => None,
+    }
+  }
+
+  /// A human readable rendering of this content when it is used as a map key.
+  pub fn key_label(&self) -> String {
+    match *self {
+      Content::Bool(b) => b.to_string(),
+      Content::U8(n) => n.to_string(),
+      Content::U16(n) => n.to_string(),
+      Content::U32(n) => n.to_string(),
+      Content::U64(n) => n.to_string(),
+      Content::I8(n) => n.to_string(),
+      Content::I16(n) => n.to_string(),
+      Content::I32(n) => n.to_string(),
+      Content::I64(n) => n.to_string(),
+      Content::F32(f) => f.to_string(),
+      Content::F64(f) => f.to_string(),
+      Content::Char(c) => c.to_string(),
+      Content::Some(ref x) | Content::Newtype(ref x) => x.key_label(),
+      Content::None | Content::Unit => "()".to_owned(),
+      ref other => match other.as_str() {
+        Some(x) => x.to_owned(),
+        None => format!("<{}>", other.unexpected()),
+      },
+    }
+  }
+
+  pub fn unexpected(&self) -> Unexpected<'_> {
+    match *self {
+      Content::Bool(b) => Unexpected::Bool(b),
+      Content::U8(n) => Unexpected::Unsigned(n as u64),
+      Content::U16(n) => Unexpected::Unsigned(n as u64),
+      Content::U32(n) => Unexpected::Unsigned(n as u64),
+      Content::U64(n) => Unexpected::Unsigned(n),
+      Content::I8(n) => Unexpected::Signed(n as i64),
+      Content::I16(n) => Unexpected::Signed(n as i64),
+      Content::I32(n) => Unexpected::Signed(n as i64),
+      Content::I64(n) => Unexpected::Signed(n),
+      Content::F32(f) => Unexpected::Float(f as f64),
+      Content::F64(f) => Unexpected::Float(f),
+      Content::Char(c) => Unexpected::Char(c),
+      Content::String(ref s) => Unexpected::Str(s),
+      Content::Str(s) => Unexpected::Str(s),
+      Content::ByteBuf(ref b) => Unexpected::Bytes(b),
+      Content::Bytes(b) => Unexpected::Bytes(b),
+      Content::None | Content::Some(_) => Unexpected::Option,
+      Content::Unit => Unexpected::Unit,
+      Content::Newtype(_) => Unexpected::NewtypeStruct,
+      Content::Seq(_) => Unexpected::Seq,
+      Content::Map(_) => Unexpected::Map,
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for Content<'de> {
+  fn deserialize<D>(de: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    de.deserialize_any(ContentVisitor)
+  }
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+  type Value = Content<'de>;
+
+  fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    fmt.write_str("any value")
+  }
+
+  fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+    Ok(Content::Bool(value))
+  }
+
+  fn visit_i8<E>(self, value: i8) -> Result<Self::Value, E> {
+    Ok(Content::I8(value))
+  }
+
+  fn visit_i16<E>(self, value: i16) -> Result<Self::Value, E> {
+    Ok(Content::I16(value))
+  }
+
+  fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E> {
+    Ok(Content::I32(value))
+  }
+
+  fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+    Ok(Content::I64(value))
+  }
+
+  fn visit_u8<E>(self, value: u8) -> Result<Self::Value, E> {
+    Ok(Content::U8(value))
+  }
+
+  fn visit_u16<E>(self, value: u16) -> Result<Self::Value, E> {
+    Ok(Content::U16(value))
+  }
+
+  fn visit_u32<E>(self, value: u32) -> Result<Self::Value, E> {
+    Ok(Content::U32(value))
+  }
+
+  fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+    Ok(Content::U64(value))
+  }
+
+  fn visit_f32<E>(self, value: f32) -> Result<Self::Value, E> {
+    Ok(Content::F32(value))
+  }
+
+  fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+    Ok(Content::F64(value))
+  }
+
+  fn visit_char<E>(self, value: char) -> Result<Self::Value, E> {
+    Ok(Content::Char(value))
+  }
+
+  fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+    Ok(Content::String(value.to_owned()))
+  }
+
+  fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E> {
+    Ok(Content::Str(value))
+  }
+
+  fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+    Ok(Content::String(value))
+  }
+
+  fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E> {
+    Ok(Content::ByteBuf(value.to_owned()))
+  }
+
+  fn visit_borrowed_bytes<E>(self, value: &'de [u8]) -> Result<Self::Value, E> {
+    Ok(Content::Bytes(value))
+  }
+
+  fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+    Ok(Content::ByteBuf(value))
+  }
+
+  fn visit_unit<E>(self) -> Result<Self::Value, E> {
+    Ok(Content::Unit)
+  }
+
+  fn visit_none<E>(self) -> Result<Self::Value, E> {
+    Ok(Content::None)
+  }
+
+  fn visit_some<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    Content::deserialize(de).map(|x| Content::Some(Box::new(x)))
+  }
+
+  fn visit_newtype_struct<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    Content::deserialize(de).map(|x| Content::Newtype(Box::new(x)))
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+    while let Some(elem) = seq.next_element()? {
+      vec.push(elem);
+    }
+
+    Ok(Content::Seq(vec))
+  }
+
+  fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+  where
+    A: MapAccess<'de>,
+  {
+    let mut vec = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+    while let Some(entry) = map.next_entry()? {
+      vec.push(entry);
+    }
+
+    Ok(Content::Map(vec))
+  }
+
+  fn visit_enum<A>(self, _: A) -> Result<Self::Value, A::Error>
+  where
+    A: EnumAccess<'de>,
+  {
+    Err(A::Error::custom(
+      "untagged and internally tagged enums do not support enum input",
+    ))
+  }
+}
+
+/// A deserializer which replays buffered [`Content`].
+#[doc(hidden)]
+pub struct ContentDeserializer<'de, E> {
+  content: Content<'de>,
+  err: PhantomData<E>,
+}
+
+impl<'de, E> ContentDeserializer<'de, E> {
+  pub fn new(content: Content<'de>) -> Self {
+    Self {
+      content,
+      err: PhantomData,
+    }
+  }
+}
+
+fn visit_content_seq<'de, V, E>(content: Vec<Content<'de>>, visitor: V) -> Result<V::Value, E>
+where
+  V: Visitor<'de>,
+  E: Error,
+{
+  let mut seq = SeqDeserializer::new(content.into_iter());
+  let value = visitor.visit_seq(&mut seq)?;
+  seq.end()?;
+  Ok(value)
+}
+
+fn visit_content_map<'de, V, E>(
+  content: Vec<(Content<'de>, Content<'de>)>,
+  visitor: V,
+) -> Result<V::Value, E>
+where
+  V: Visitor<'de>,
+  E: Error,
+{
+  let mut map = MapDeserializer::new(content.into_iter());
+  let value = visitor.visit_map(&mut map)?;
+  map.end()?;
+  Ok(value)
+}
+
+impl<'de, E> Deserializer<'de> for ContentDeserializer<'de, E>
+where
+  E: Error,
+{
+  type Error = E;
+
+  fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    match self.content {
+      Content::Bool(v) => visitor.visit_bool(v),
+      Content::U8(v) => visitor.visit_u8(v),
+      Content::U16(v) => visitor.visit_u16(v),
+      Content::U32(v) => visitor.visit_u32(v),
+      Content::U64(v) => visitor.visit_u64(v),
+      Content::I8(v) => visitor.visit_i8(v),
+      Content::I16(v) => visitor.visit_i16(v),
+      Content::I32(v) => visitor.visit_i32(v),
+      Content::I64(v) => visitor.visit_i64(v),
+      Content::F32(v) => visitor.visit_f32(v),
+      Content::F64(v) => visitor.visit_f64(v),
+      Content::Char(v) => visitor.visit_char(v),
+      Content::String(v) => visitor.visit_string(v),
+      Content::Str(v) => visitor.visit_borrowed_str(v),
+      Content::ByteBuf(v) => visitor.visit_byte_buf(v),
+      Content::Bytes(v) => visitor.visit_borrowed_bytes(v),
+      Content::Unit => visitor.visit_unit(),
+      Content::None => visitor.visit_none(),
+      Content::Some(v) => visitor.visit_some(ContentDeserializer::new(*v)),
+      Content::Newtype(v) => visitor.visit_newtype_struct(ContentDeserializer::new(*v)),
+      Content::Seq(v) => visit_content_seq(v, visitor),
+      Content::Map(v) => visit_content_map(v, visitor),
+    }
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    match self.content {
+      Content::None => visitor.visit_none(),
+      Content::Some(v) => visitor.visit_some(ContentDeserializer::new(*v)),
+      Content::Unit => visitor.visit_unit(),
+      _ => visitor.visit_some(self),
+    }
+  }
+
+  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    match self.content {
+      Content::Newtype(v) => visitor.visit_newtype_struct(ContentDeserializer::new(*v)),
+      _ => visitor.visit_newtype_struct(self),
+    }
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    let (variant, value) = match self.content {
+      Content::Map(value) => {
+        let mut iter = value.into_iter();
+        let (variant, value) = match iter.next() {
+          Some(entry) => entry,
+          None => return Err(E::invalid_value(Unexpected::Map, &"map with a single key")),
+        };
+
+        if iter.next().is_some() {
+          return Err(E::invalid_value(Unexpected::Map, &"map with a single key"));
+        }
+
+        (variant, Some(value))
+      }
+      s @ Content::String(_) | s @ Content::Str(_) => (s, None),
+      other => return Err(E::invalid_type(other.unexpected(), &"string or map")),
+    };
+
+    visitor.visit_enum(EnumDeserializer {
+      variant,
+      value,
+      err: PhantomData,
+    })
+  }
+
+  fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    drop(self);
+    visitor.visit_unit()
+  }
+
+  serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+    identifier
+  }
+}
+
+impl<'de, E> IntoDeserializer<'de, E> for Content<'de>
+where
+  E: Error,
+{
+  type Deserializer = ContentDeserializer<'de, E>;
+
+  fn into_deserializer(self) -> Self::Deserializer {
+    ContentDeserializer::new(self)
+  }
+}
+
+struct EnumDeserializer<'de, E> {
+  variant: Content<'de>,
+  value: Option<Content<'de>>,
+  err: PhantomData<E>,
+}
+
+impl<'de, E> EnumAccess<'de> for EnumDeserializer<'de, E>
+where
+  E: Error,
+{
+  type Error = E;
+  type Variant = VariantDeserializer<'de, E>;
+
+  fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), E>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    let visitor = VariantDeserializer {
+      value: self.value,
+      err: PhantomData,
+    };
+
+    seed
+      .deserialize(ContentDeserializer::new(self.variant))
+      .map(|v| (v, visitor))
+  }
+}
+
+struct VariantDeserializer<'de, E> {
+  value: Option<Content<'de>>,
+  err: PhantomData<E>,
+}
+
+impl<'de, E> VariantAccess<'de> for VariantDeserializer<'de, E>
+where
+  E: Error,
+{
+  type Error = E;
+
+  fn unit_variant(self) -> Result<(), E> {
+    match self.value {
+      Some(value) => Deserialize::deserialize(ContentDeserializer::new(value)),
+      None => Ok(()),
+    }
+  }
+
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, E>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    match self.value {
+      Some(value) => seed.deserialize(ContentDeserializer::new(value)),
+      None => Err(E::invalid_type(Unexpected::UnitVariant, &"newtype variant")),
+    }
+  }
+
+  fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    match self.value {
+      Some(Content::Seq(v)) => visit_content_seq(v, visitor),
+      Some(other) => Err(E::invalid_type(other.unexpected(), &"tuple variant")),
+      None => Err(E::invalid_type(Unexpected::UnitVariant, &"tuple variant")),
+    }
+  }
+
+  fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    match self.value {
+      Some(Content::Map(v)) => visit_content_map(v, visitor),
+      Some(Content::Seq(v)) => visit_content_seq(v, visitor),
+      Some(other) => Err(E::invalid_type(other.unexpected(), &"struct variant")),
+      None => Err(E::invalid_type(Unexpected::UnitVariant, &"struct variant")),
+    }
+  }
+}
+
+/// A deserializer over the entries of a map which were not claimed by any
+/// of the named fields of a struct containing `#[serde(flatten)]` fields.
+///
+/// Structs and enums take the entries they use so that they aren't passed on
+/// to later flattened fields while maps see all remaining entries.
+#[doc(hidden)]
+pub struct FlatMapDeserializer<'a, 'de, E> {
+  entries: &'a mut Vec<Option<(Content<'de>, Content<'de>)>>,
+  err: PhantomData<E>,
+}
+
+impl<'a, 'de, E> FlatMapDeserializer<'a, 'de, E> {
+  pub fn new(entries: &'a mut Vec<Option<(Content<'de>, Content<'de>)>>) -> Self {
+    Self {
+      entries,
+      err: PhantomData,
+    }
+  }
+}
+
+impl<'a, 'de, E> Deserializer<'de> for FlatMapDeserializer<'a, 'de, E>
+where
+  E: Error,
+{
+  type Error = E;
+
+  fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_map(visitor)
+  }
+
+  fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_map(FlatMapAccess {
+      iter: self.entries.iter(),
+      value: None,
+      err: PhantomData,
+    })
+  }
+
+  fn deserialize_struct<V>(
+    self,
+    _name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_map(FlatStructAccess {
+      iter: self.entries.iter_mut(),
+      fields,
+      value: None,
+      err: PhantomData,
+    })
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    name: &'static str,
+    variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    let entry = self.entries.iter_mut().find(|entry| match entry {
+      Some((key, _)) => matches!(key.as_str(), Some(key) if variants.contains(&key)),
+      None => false,
+    });
+
+    match entry.and_then(Option::take) {
+      Some((variant, value)) => visitor.visit_enum(EnumDeserializer {
+        variant,
+        value: Some(value),
+        err: PhantomData,
+      }),
+      None => Err(E::custom(format_args!(
+        "no variant of enum {} found in flattened data",
+        name
+      ))),
+    }
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_some(self)
+  }
+
+  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf seq tuple tuple_struct identifier
+  }
+}
+
+struct FlatMapAccess<'a, 'de, E> {
+  iter: std::slice::Iter<'a, Option<(Content<'de>, Content<'de>)>>,
+  value: Option<&'a Content<'de>>,
+  err: PhantomData<E>,
+}
+
+impl<'a, 'de, E> MapAccess<'de> for FlatMapAccess<'a, 'de, E>
+where
+  E: Error,
+{
+  type Error = E;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, E>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    match self.iter.by_ref().flatten().next() {
+      Some((key, value)) => {
+        self.value = Some(value);
+        seed
+          .deserialize(ContentDeserializer::new(key.clone()))
+          .map(Some)
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, E>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    match self.value.take() {
+      Some(value) => seed.deserialize(ContentDeserializer::new(value.clone())),
+      None => Err(E::custom("value is missing")),
+    }
+  }
+}
+
+struct FlatStructAccess<'a, 'de, E> {
+  iter: std::slice::IterMut<'a, Option<(Content<'de>, Content<'de>)>>,
+  fields: &'static [&'static str],
+  value: Option<Content<'de>>,
+  err: PhantomData<E>,
+}
+
+impl<'a, 'de, E> MapAccess<'de> for FlatStructAccess<'a, 'de, E>
+where
+  E: Error,
+{
+  type Error = E;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, E>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    let fields = self.fields;
+
+    for entry in self.iter.by_ref() {
+      let claimed = match entry {
+        Some((key, _)) => matches!(key.as_str(), Some(key) if fields.contains(&key)),
+        None => false,
+      };
+
+      if !claimed {
+        continue;
+      }
+
+      if let Some((key, value)) = entry.take() {
+        self.value = Some(value);
+        return seed.deserialize(ContentDeserializer::new(key)).map(Some);
+      }
+    }
+
+    Ok(None)
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, E>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    match self.value.take() {
+      Some(value) => seed.deserialize(ContentDeserializer::new(value)),
+      None => Err(E::custom("value is missing")),
+    }
+  }
+}
+
+/// Whether a flattened `T` would take any of the collected `entries`.
+///
+/// This runs `T`'s `Deserialize` impl against a deserializer which only looks
+/// at the entries a struct or enum would claim and then bails out, so nothing
+/// is actually deserialized.
+#[doc(hidden)]
+pub fn flat_entries_present<'de, T>(entries: &[Option<(Content<'de>, Content<'de>)>]) -> bool
+where
+  T: Deserialize<'de>,
+{
+  let mut present = false;
+  let _ = T::deserialize(FlatProbe {
+    entries,
+    present: &mut present,
+  });
+  present
+}
+
+struct FlatProbe<'a, 'de> {
+  entries: &'a [Option<(Content<'de>, Content<'de>)>],
+  present: &'a mut bool,
+}
+
+impl<'a, 'de> FlatProbe<'a, 'de> {
+  /// Record whether any entry has one of `names`, or any entry at all if
+  /// there are none, and return the error that stops deserialization.
+  fn claims(self, names: Option<&[&str]>) -> serde::de::value::Error {
+    *self.present = self.entries.iter().flatten().any(|(key, _)| match names {
+      Some(names) => matches!(key.as_str(), Some(key) if names.contains(&key)),
+      None => true,
+    });
+
+    serde::de::value::Error::custom("flattened entries probed")
+  }
+}
+
+impl<'a, 'de> Deserializer<'de> for FlatProbe<'a, 'de> {
+  type Error = serde::de::value::Error;
+
+  fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    Err(self.claims(None))
+  }
+
+  fn deserialize_struct<V>(
+    self,
+    _name: &'static str,
+    fields: &'static [&'static str],
+    _visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    Err(self.claims(Some(fields)))
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    _name: &'static str,
+    variants: &'static [&'static str],
+    _visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    Err(self.claims(Some(variants)))
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_some(self)
+  }
+
+  fn deserialize_newtype_struct<V>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    Err(self.claims(Some(&[])))
+  }
+
+  fn deserialize_unit_struct<V>(
+    self,
+    _name: &'static str,
+    _visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    Err(self.claims(Some(&[])))
+  }
+
+  serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf seq tuple tuple_struct map identifier ignored_any
+  }
+}
+
+/// Split the tag out of the content of an internally tagged enum.
+///
+/// Returns the deserialized tag along with the remaining content.
+#[doc(hidden)]
+pub fn take_internal_tag<'de, T, E>(
+  content: Content<'de>,
+  tag: &'static str,
+  expecting: &'static str,
+) -> Result<(T, Content<'de>), E>
+where
+  T: Deserialize<'de>,
+  E: Error,
+{
+  match content {
+    Content::Map(mut entries) => {
+      let index = entries
+        .iter()
+        .position(|(key, _)| key.as_str() == Some(tag))
+        .ok_or_else(|| E::missing_field(tag))?;
+      let (_, value) = entries.remove(index);
+
+      if entries.iter().any(|(key, _)| key.as_str() == Some(tag)) {
+        return Err(E::duplicate_field(tag));
+      }
+
+      let tag = T::deserialize(ContentDeserializer::new(value))?;
+      Ok((tag, Content::Map(entries)))
+    }
+    Content::Seq(mut elements) => {
+      if elements.is_empty() {
+        return Err(E::invalid_length(0, &expecting));
+      }
+
+      let value = elements.remove(0);
+      let tag = T::deserialize(ContentDeserializer::new(value))?;
+      Ok((tag, Content::Seq(elements)))
+    }
+    other => Err(E::invalid_type(other.unexpected(), &expecting)),
+  }
+}
+
+/// Split the content of an adjacently tagged enum into its tag and content.
+#[doc(hidden)]
+pub fn take_adjacent_tag<'de, T, E>(
+  content: Content<'de>,
+  tag: &'static str,
+  value: &'static str,
+  expecting: &'static str,
+) -> Result<(T, Option<Content<'de>>), E>
+where
+  T: Deserialize<'de>,
+  E: Error,
+{
+  let (tag_content, value_content) = match content {
+    Content::Map(entries) => {
+      let mut tag_content = None;
+      let mut value_content = None;
+
+      for (key, entry) in entries {
+        let (name, slot) = match key.as_str() {
+          Some(key) if key == tag => (tag, &mut tag_content),
+          Some(key) if key == value => (value, &mut value_content),
+          _ => continue,
+        };
+
+        if slot.is_some() {
+          return Err(E::duplicate_field(name));
+        }
+
+        *slot = Some(entry);
+      }
+
+      (
+        tag_content.ok_or_else(|| E::missing_field(tag))?,
+        value_content,
+      )
+    }
+    Content::Seq(elements) => {
+      let len = elements.len();
+      let mut iter = elements.into_iter();
+
+      match (iter.next(), iter.next(), iter.next()) {
+        (Some(tag), value, None) => (tag, value),
+        _ => return Err(E::invalid_length(len, &expecting)),
+      }
+    }
+    other => return Err(E::invalid_type(other.unexpected(), &expecting)),
+  };
+
+  let tag = T::deserialize(ContentDeserializer::new(tag_content))?;
+  Ok((tag, value_content))
+}
@@ -0,0 +1,418 @@
+//! State threaded through a single `deserialize_over` call.
+
+use std::fmt;
+
+/// State which is passed down through nested
+/// [`deserialize_over_with`](crate::DeserializeOver::deserialize_over_with)
+/// calls.
+#[derive(Debug, Default)]
+pub struct Context {
+  dry_run: bool,
+  merge_patch: bool,
+  error_paths: bool,
+  collect_errors: bool,
+  path: Vec<Segment>,
+  changes: Option<Vec<String>>,
+  error_path: Option<String>,
+  rejected: Vec<Rejected>,
+  unknown_keys: Option<Vec<UnknownKey>>,
+}
+
+/// A value which was skipped because it failed to deserialize.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejected {
+  /// The dotted path of the value.
+  pub path: String,
+  /// The error message for the value.
+  pub message: String,
+}
+
+impl fmt::Display for Rejected {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self.path.is_empty() {
+      true => fmt.write_str(&self.message),
+      false => write!(fmt, "{}: {}", self.path, self.message),
+    }
+  }
+}
+
+/// A key in the input which does not match any field of the struct it was
+/// found in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownKey {
+  /// The dotted path of the key, ending in the key itself.
+  pub path: String,
+  /// The known field that is closest to the key, if any are similar.
+  pub suggestion: Option<&'static str>,
+}
+
+impl fmt::Display for UnknownKey {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "unknown field `{}`", self.path)?;
+    if let Some(suggestion) = self.suggestion {
+      write!(fmt, ", did you mean `{}`?", suggestion)?;
+    }
+    Ok(())
+  }
+}
+
+/// The outcome of a lenient
+/// [`deserialize_over_lenient`](crate::DeserializeOver::deserialize_over_lenient)
+/// call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+  /// The paths of all values that were written.
+  pub applied: Vec<String>,
+  /// The values that failed to deserialize and were skipped.
+  pub rejected: Vec<Rejected>,
+  /// The keys that did not match any field.
+  pub unknown_keys: Vec<UnknownKey>,
+}
+
+impl Report {
+  /// Whether every value in the input was applied.
+  pub fn is_clean(&self) -> bool {
+    self.rejected.is_empty() && self.unknown_keys.is_empty()
+  }
+}
+
+/// A single component of the path to the value currently being deserialized.
+#[derive(Debug)]
+enum Segment {
+  Field(&'static str),
+  Index(usize),
+  Key(String),
+}
+
+impl fmt::Display for Segment {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Field(name) => fmt.write_str(name),
+      Self::Index(index) => write!(fmt, "[{}]", index),
+      Self::Key(key) => fmt.write_str(key),
+    }
+  }
+}
+
+impl Context {
+  /// Create a context for a regular `deserialize_over` call.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Create a context for a dry run.
+  ///
+  /// During a dry run the input is fully deserialized and validated but the
+  /// target is left untouched.
+  pub fn dry_run() -> Self {
+    Self {
+      dry_run: true,
+      ..Self::default()
+    }
+  }
+
+  /// Create a context which records the path of every value that is written.
+  pub fn tracking_changes() -> Self {
+    Self {
+      changes: Some(Vec::new()),
+      ..Self::default()
+    }
+  }
+
+  /// Apply the input as a JSON Merge Patch ([RFC 7396]), where `null` removes
+  /// or resets the corresponding value.
+  ///
+  /// [RFC 7396]: https://www.rfc-editor.org/rfc/rfc7396
+  pub fn with_merge_patch(mut self) -> Self {
+    self.merge_patch = true;
+    self
+  }
+
+  /// Remember the path at which deserialization failed so that it can be
+  /// retrieved with [`error_path`](Self::error_path).
+  pub fn with_error_paths(mut self) -> Self {
+    self.error_paths = true;
+    self
+  }
+
+  /// Record fields which fail to deserialize and skip them instead of
+  /// stopping at the first error.
+  ///
+  /// Errors in the input itself, such as malformed JSON, still stop
+  /// deserialization. This also enables [error paths](Self::with_error_paths)
+  /// so that errors are recorded against the innermost value.
+  pub fn with_collected_errors(mut self) -> Self {
+    self.collect_errors = true;
+    self.error_paths = true;
+    self
+  }
+
+  /// Record keys which don't match any field instead of silently ignoring
+  /// them.
+  pub fn with_unknown_keys(mut self) -> Self {
+    self.unknown_keys = Some(Vec::new());
+    self
+  }
+
+  /// A dry run which applies the input the same way as this context and
+  /// records unknown keys, for checking whether the input fits the current
+  /// value before deserializing over it.
+  #[doc(hidden)]
+  pub fn probe(&self) -> Self {
+    Self {
+      dry_run: true,
+      merge_patch: self.merge_patch,
+      unknown_keys: Some(Vec::new()),
+      ..Self::default()
+    }
+  }
+
+  /// Whether this is a dry run, in which case implementations must not modify
+  /// the target.
+  pub fn is_dry_run(&self) -> bool {
+    self.dry_run
+  }
+
+  /// Whether `null` in the input removes or resets values.
+  pub fn is_merge_patch(&self) -> bool {
+    self.merge_patch
+  }
+
+  /// Whether changes are being recorded. Implementations can use this to skip
+  /// work that is only needed to describe the current path.
+  pub fn is_tracking_changes(&self) -> bool {
+    self.changes.is_some()
+  }
+
+  /// Whether failing fields are recorded and skipped.
+  pub fn is_collecting_errors(&self) -> bool {
+    self.collect_errors
+  }
+
+  /// Enable or disable collecting errors, returning the previous setting.
+  ///
+  /// Implementations which try several ways of deserializing a value need
+  /// errors to propagate so this allows them to turn it off temporarily.
+  pub fn set_collecting_errors(&mut self, collect: bool) -> bool {
+    std::mem::replace(&mut self.collect_errors, collect)
+  }
+
+  /// Whether unknown keys are being recorded.
+  pub fn is_tracking_unknown_keys(&self) -> bool {
+    self.unknown_keys.is_some()
+  }
+
+  /// Whether the current path is being tracked, either to record changes and
+  /// unknown keys or to report where errors occurred.
+  pub fn is_tracking_path(&self) -> bool {
+    self.error_paths || self.is_tracking_changes() || self.is_tracking_unknown_keys()
+  }
+
+  /// Enter the struct field `name`.
+  pub fn push_field(&mut self, name: &'static str) {
+    if self.is_tracking_path() {
+      self.path.push(Segment::Field(name));
+    }
+  }
+
+  /// Enter the element at `index` of a sequence.
+  pub fn push_index(&mut self, index: usize) {
+    if self.is_tracking_path() {
+      self.path.push(Segment::Index(index));
+    }
+  }
+
+  /// Enter the value for `key` within a map.
+  pub fn push_key(&mut self, key: impl fmt::Display) {
+    if self.is_tracking_path() {
+      self.path.push(Segment::Key(key.to_string()));
+    }
+  }
+
+  /// Leave the innermost field, element or map entry.
+  pub fn pop(&mut self) {
+    self.path.pop();
+  }
+
+  /// Leave the innermost field, element or map entry after deserializing it.
+  ///
+  /// If `result` is the first error seen then the current path is remembered
+  /// as the error path. A successful result clears any error path left behind
+  /// by an error that was recovered from.
+  pub fn pop_result<T, E>(&mut self, result: Result<T, E>) -> Result<T, E> {
+    match result {
+      Ok(_) => self.error_path = None,
+      Err(_) if self.error_paths && self.error_path.is_none() => {
+        self.error_path = Some(self.path())
+      }
+      Err(_) => (),
+    }
+
+    self.path.pop();
+    result
+  }
+
+  /// The dotted path of the value currently being deserialized, with
+  /// sequence indices in brackets such as `servers[1].port`.
+  pub fn path(&self) -> String {
+    let mut path = String::new();
+    for (i, segment) in self.path.iter().enumerate() {
+      if i > 0 && !matches!(segment, Segment::Index(_)) {
+        path.push('.');
+      }
+      path.push_str(&segment.to_string());
+    }
+    path
+  }
+
+  /// The path of the innermost value that failed to deserialize, if error
+  /// paths are enabled and an error has occurred.
+  pub fn error_path(&self) -> Option<&str> {
+    self.error_path.as_deref()
+  }
+
+  /// Forget the error path after recovering from an error.
+  pub fn clear_error_path(&mut self) {
+    self.error_path = None;
+  }
+
+  /// Record an error for the value at the current path, or for the innermost
+  /// value which failed within it.
+  pub fn record_error(&mut self, message: impl fmt::Display) {
+    let path = match self.error_path.take() {
+      Some(path) => path,
+      None => self.path(),
+    };
+
+    self.rejected.push(Rejected {
+      path,
+      message: message.to_string(),
+    });
+  }
+
+  /// Record and discard the error in `result` if errors are being collected.
+  pub fn recover<E>(&mut self, result: Result<(), E>) -> Result<(), E>
+  where
+    E: fmt::Display,
+  {
+    match result {
+      Err(e) if self.collect_errors => {
+        self.record_error(e);
+        Ok(())
+      }
+      result => result,
+    }
+  }
+
+  /// The values that have been rejected so far.
+  pub fn rejected(&self) -> &[Rejected] {
+    &self.rejected
+  }
+
+  /// Record that `key` within the current struct did not match any of the
+  /// `expected` fields.
+  pub fn record_unknown_key(&mut self, key: &str, expected: &'static [&'static str]) {
+    if !self.is_tracking_unknown_keys() {
+      return;
+    }
+
+    let path = match self.path.is_empty() {
+      true => key.to_owned(),
+      false => format!("{}.{}", self.path(), key),
+    };
+    let suggestion = closest_match(key, expected);
+
+    self
+      .unknown_keys
+      .get_or_insert_with(Vec::new)
+      .push(UnknownKey { path, suggestion });
+  }
+
+  /// The unknown keys recorded so far.
+  pub fn unknown_keys(&self) -> &[UnknownKey] {
+    self.unknown_keys.as_deref().unwrap_or(&[])
+  }
+
+  /// Discard all but the first `len` unknown keys. Implementations which try
+  /// several ways of deserializing a value use this to forget the keys seen by
+  /// attempts that failed.
+  pub fn truncate_unknown_keys(&mut self, len: usize) {
+    if let Some(unknown_keys) = &mut self.unknown_keys {
+      unknown_keys.truncate(len);
+    }
+  }
+
+  /// Consume the context, returning the unknown keys that were recorded.
+  pub fn into_unknown_keys(self) -> Vec<UnknownKey> {
+    self.unknown_keys.unwrap_or_default()
+  }
+
+  /// Record that the value at the current path was written.
+  pub fn record_change(&mut self) {
+    if self.is_tracking_changes() {
+      let path = self.path();
+      self.changes.get_or_insert_with(Vec::new).push(path);
+    }
+  }
+
+  /// The paths of all values written so far, in the order they were written.
+  pub fn changes(&self) -> &[String] {
+    self.changes.as_deref().unwrap_or(&[])
+  }
+
+  /// Consume the context, returning the paths of all values that were written.
+  pub fn into_changes(self) -> Vec<String> {
+    self.changes.unwrap_or_default()
+  }
+
+  /// Consume the context, returning the values that were written and those
+  /// that were rejected.
+  pub fn into_report(self) -> Report {
+    Report {
+      applied: self.changes.unwrap_or_default(),
+      rejected: self.rejected,
+      unknown_keys: self.unknown_keys.unwrap_or_default(),
+    }
+  }
+}
+
+/// Find the name in `candidates` with the smallest edit distance to `key`, as
+/// long as it is close enough to plausibly be a typo.
+fn closest_match(key: &str, candidates: &[&'static str]) -> Option<&'static str> {
+  let limit = (key.chars().count() / 3).max(1);
+
+  candidates
+    .iter()
+    .map(|&candidate| (edit_distance(key, candidate), candidate))
+    .filter(|&(distance, _)| distance <= limit)
+    .min_by_key(|&(distance, _)| distance)
+    .map(|(_, candidate)| candidate)
+}
+
+/// The edit distance between `a` and `b`, counting insertions, deletions,
+/// substitutions and transpositions of adjacent characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let a = a.chars().collect::<Vec<_>>();
+  let b = b.chars().collect::<Vec<_>>();
+  let mut prev2 = vec![0; b.len() + 1];
+  let mut prev = (0..=b.len()).collect::<Vec<_>>();
+  let mut row = vec![0; b.len() + 1];
+
+  for i in 1..=a.len() {
+    row[0] = i;
+
+    for j in 1..=b.len() {
+      let cost = (a[i - 1] != b[j - 1]) as usize;
+      row[j] = (prev[j - 1] + cost).min(prev[j] + 1).min(row[j - 1] + 1);
+
+      if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+        row[j] = row[j].min(prev2[j - 2] + 1);
+      }
+    }
+
+    std::mem::swap(&mut prev2, &mut prev);
+    std::mem::swap(&mut prev, &mut row);
+  }
+
+  prev[b.len()]
+}
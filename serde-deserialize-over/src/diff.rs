@@ -0,0 +1,78 @@
+//! Diffs between two values generated by `#[deserialize_over(diff)]`.
+
+use serde::{Serialize, Serializer};
+
+/// Types which can describe the changes between two values in a form that can
+/// be deserialized over the old value.
+///
+/// Deserializing the output of [`serialize_diff`](Diff::serialize_diff) over
+/// the old value with [`DeserializeOver`](crate::DeserializeOver) gives a
+/// value equal to the new one. Changes which can't be expressed that way, such
+/// as removing elements from a sequence that is merged by index or clearing an
+/// option which ignores `null`, are reported as serialization errors.
+pub trait Diff {
+  /// Whether `new` differs from `self`.
+  fn differs(&self, new: &Self) -> bool;
+
+  /// Serialize the changes needed to turn `self` into `new`.
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer;
+
+  /// The changes needed to turn `self` into `new` as a value which can be
+  /// passed to any serializer.
+  fn diff<'a>(&'a self, new: &'a Self) -> DiffOf<'a, Self> {
+    DiffOf(self, new)
+  }
+}
+
+/// The changes between two values, as returned by [`Diff::diff`].
+pub struct DiffOf<'a, T: ?Sized>(&'a T, &'a T);
+
+impl<'a, T> DiffOf<'a, T>
+where
+  T: Diff + ?Sized,
+{
+  /// Whether there are any changes at all.
+  pub fn differs(&self) -> bool {
+    self.0.differs(self.1)
+  }
+}
+
+impl<'a, T> Serialize for DiffOf<'a, T>
+where
+  T: Diff + ?Sized,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    self.0.serialize_diff(self.1, ser)
+  }
+}
+
+/// The diff of a value which is replaced as a whole: the new value if it
+/// differs from the old one.
+#[doc(hidden)]
+pub struct ReplaceDiff<'a, T: ?Sized>(pub &'a T, pub &'a T);
+
+impl<'a, T> ReplaceDiff<'a, T>
+where
+  T: PartialEq + ?Sized,
+{
+  pub fn differs(&self) -> bool {
+    self.0 != self.1
+  }
+}
+
+impl<'a, T> Serialize for ReplaceDiff<'a, T>
+where
+  T: Serialize + ?Sized,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    self.1.serialize(ser)
+  }
+}
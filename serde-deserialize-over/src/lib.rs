@@ -77,6 +77,633 @@
 //! assert_eq!(inst.c, 0);
 //! ```
 //!
+//! Fields marked `#[serde(flatten)]` receive all the keys which aren't
+//! claimed by the other fields of the struct. As with any other field they are
+//! deserialized over the existing value when marked `#[deserialize_over]` and
+//! replaced otherwise. A flattened field that is replaced is only touched when
+//! the input has at least one of its keys, so a patch for the rest of the
+//! struct leaves it as it is.
+//!
+//! # Vectors
+//! A `#[deserialize_over]` field holding a `Vec`, `VecDeque` or `LinkedList` is
+//! merged element by element:
+//! element N of the input is deserialized over element N of the vector, extra
+//! elements are pushed onto the end and elements past the end of the input are
+//! kept. A different strategy can be selected with
+//! `#[deserialize_over(vec = "...")]`:
+//! - `"by_index"`: the default described above.
+//! - `"append"`: all elements of the input are appended to the vector.
+//! - `"replace"`: the vector is replaced entirely, the same as leaving off the
+//!   `#[deserialize_over]` attribute.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct MyStruct {
+//!     #[deserialize_over(vec = "append")]
+//!     pub hosts: Vec<String>,
+//! }
+//!
+//! let json = r#"{ "hosts": ["b"] }"#;
+//! let mut inst = MyStruct { hosts: vec!["a".to_owned()] };
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_over(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(inst.hosts, ["a", "b"]);
+//! ```
+//!
+//! Vectors of structs can instead be merged by a key field using
+//! `#[deserialize_over(merge_by = "name")]`. Each element of the input is
+//! deserialized over the existing element with the same `name`, or appended if
+//! there is none. With `tombstone = "_delete"` as well, elements which have
+//! `"_delete": true` remove the matching element instead. The key is the Rust
+//! name of a field of the element type and is looked up in the input under the
+//! names that field is deserialized from, so `rename`, `rename_all` and `alias`
+//! on the element type are taken into account. Since elements are buffered
+//! this requires a self-describing format.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_derive::Deserialize;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Deserialize)]
+//! struct Server {
+//!     name: String,
+//!     port: u16,
+//! }
+//!
+//! #[derive(DeserializeOver)]
+//! struct Config {
+//!     #[deserialize_over(merge_by = "name", tombstone = "_delete")]
+//!     servers: Vec<Server>,
+//! }
+//!
+//! let mut inst = Config {
+//!     servers: vec![
+//!         Server { name: "a".to_owned(), port: 80 },
+//!         Server { name: "b".to_owned(), port: 80 },
+//!     ],
+//! };
+//!
+//! let json = r#"{ "servers": [{ "name": "b", "port": 81 }, { "name": "a", "_delete": true }] }"#;
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_over(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(inst.servers.len(), 1);
+//! assert_eq!(inst.servers[0].port, 81);
+//! ```
+//!
+//! # Maps
+//! Entries of a `#[deserialize_over]` `HashMap` or `BTreeMap` are deserialized
+//! over the existing value for the same key or inserted if there is none. To
+//! let a patch remove entries, mark the field with
+//! `#[deserialize_over(delete_null)]` so that `null` values delete the entry,
+//! or with `#[deserialize_over(tombstone = "...")]` so that values equal to the
+//! given string do. Both of these require a self-describing format.
+//!
+//! Deserializing over the existing values requires them to implement
+//! [`DeserializeOver`]. For maps of plain values such as `HashMap<String, i32>`
+//! use `#[deserialize_over(replace_values)]` instead, which merges the map by
+//! key but replaces the values themselves.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use std::collections::HashMap;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct MyStruct {
+//!     #[deserialize_over(replace_values)]
+//!     pub limits: HashMap<String, i32>,
+//! }
+//!
+//! let json = r#"{ "limits": { "b": 3 } }"#;
+//! let mut inst = MyStruct::default();
+//! inst.limits.insert("a".to_owned(), 1);
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_over(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(inst.limits["a"], 1);
+//! assert_eq!(inst.limits["b"], 3);
+//! ```
+//!
+//! # Sets
+//! A `#[deserialize_over]` `HashSet` or `BTreeSet` is extended with the
+//! elements of the input. Use `#[deserialize_over(set = "replace")]` to
+//! replace the whole set instead.
+//!
+//! # Pointers and Cells
+//! `Box`, `Cell`, `RefCell`, `Mutex` and `RwLock` deserialize over the value
+//! they contain. Since the target is borrowed mutably no locking is needed, but
+//! a poisoned `Mutex` or `RwLock` results in an error. `Rc` and `Arc` require
+//! the value to implement `Clone` and, like [`Rc::make_mut`], clone it first if
+//! it is shared with other pointers.
+//!
+//! [`Rc::make_mut`]: std::rc::Rc::make_mut
+//!
+//! # Primitives and Leaf Types
+//! Primitives, `String`, `OsString`, `PathBuf`, `Duration`, `SystemTime`, the
+//! `std::net` address types and the `NonZero*` integers implement
+//! `DeserializeOver` by replacing the whole value. This allows them to be used
+//! directly within `Option`, arrays, tuples and collections that merge their
+//! elements. `String`s reuse their existing allocation where possible.
+//!
+//! # Replace and Merge Wrappers
+//! Types from other crates often implement [`Deserialize`] but not
+//! `DeserializeOver`. Wrapping them in [`Replace`] gives a `DeserializeOver`
+//! impl which always replaces the value, so they can be used within merged
+//! containers. [`Merge`] is its counterpart and always deserializes over the
+//! value it wraps.
+//!
+//! ```
+//! use serde_deserialize_over::{DeserializeOver, Replace};
+//! # use serde_derive::Deserialize;
+//! # use std::collections::HashMap;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(Deserialize, Default)]
+//! struct Foreign {
+//!     pub a: i32,
+//!     pub b: i32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct MyStruct {
+//!     #[deserialize_over]
+//!     pub items: HashMap<String, Replace<Foreign>>,
+//! }
+//!
+//! let json = r#"{ "items": { "x": { "a": 1, "b": 2 } } }"#;
+//! let mut inst = MyStruct::default();
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_over(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(inst.items["x"].a, 1);
+//! assert_eq!(inst.items["x"].b, 2);
+//! ```
+//!
+//! # Tuple Structs
+//! Tuple structs are deserialized from sequences element by element. If the
+//! sequence is shorter than the struct then the trailing fields keep their
+//! current values. Newtype structs forward directly to their only field, so a
+//! `#[deserialize_over]` annotation on that field works as expected.
+//!
+//! # Enums
+//! The derive macro also supports enums. If the variant being deserialized is
+//! the same as the current variant then its fields are deserialized over the
+//! existing ones, otherwise the whole variant is replaced with a freshly
+//! deserialized one. Creating a fresh variant requires all of its fields to
+//! implement [`Deserialize`].
+//!
+//! The `#[serde(tag = "...")]`, `#[serde(tag = "...", content = "...")]` and
+//! `#[serde(untagged)]` enum representations are supported as well. Like with
+//! serde, these need to buffer the input and so only work with self-describing
+//! formats. Untagged enums deserialize over the current variant only if a dry
+//! run of that succeeds and uses every key in the input. Otherwise each variant
+//! is built from scratch in declaration order, the same as serde, and the value
+//! is left unchanged if none of them match.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver)]
+//! enum Backend {
+//!     Memory,
+//!     Postgres { host: String, port: u16 },
+//! }
+//!
+//! let mut inst = Backend::Postgres {
+//!     host: "localhost".to_owned(),
+//!     port: 5432,
+//! };
+//!
+//! let json = r#"{ "Postgres": { "port": 6543 } }"#;
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_over(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! match inst {
+//!     Backend::Postgres { host, port } => {
+//!         assert_eq!(host, "localhost");
+//!         assert_eq!(port, 6543);
+//!     }
+//!     Backend::Memory => unreachable!(),
+//! }
+//! ```
+//!
+//! # Transactional Updates
+//! A failed [`deserialize_over`](DeserializeOver::deserialize_over) call may
+//! leave the instance partially updated. Use
+//! [`try_deserialize_over`](DeserializeOver::try_deserialize_over) instead to
+//! either apply all of the input or none of it. It buffers the input and
+//! validates it with a dry run before touching the instance, so the input has
+//! to come from a self-describing format. Errors are prefixed with the path of
+//! the value that failed, as the position in the input is lost by buffering.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct MyStruct {
+//!     pub a: String,
+//!     pub b: i32
+//! }
+//!
+//! let json = r#"{ "a": "test", "b": "not a number" }"#;
+//! let mut inst = MyStruct::default();
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.try_deserialize_over(&mut de)
+//!     .expect_err("Deserialized invalid JSON");
+//!
+//! assert_eq!(inst.a, "");
+//! ```
+//!
+//! Types with a manual [`DeserializeOver`] impl need to implement
+//! [`deserialize_over_with`](DeserializeOver::deserialize_over_with) and
+//! respect [`Context::is_dry_run`] to take part in this.
+//!
+//! # Change Reporting
+//! [`deserialize_over_changes`](DeserializeOver::deserialize_over_changes)
+//! works like [`deserialize_over`](DeserializeOver::deserialize_over) but also
+//! returns the dotted path of every value that was written. Fields are named by
+//! their serialized name, elements of arrays and tuples by their index in
+//! brackets, as in `servers[1].port`, and map entries by their key. A value is
+//! reported when it is assigned even if the new value happens to equal the old
+//! one.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Limits {
+//!     max_conn: u32,
+//!     timeout: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Config {
+//!     name: String,
+//!     #[deserialize_over]
+//!     limits: Limits,
+//! }
+//!
+//! let json = r#"{ "limits": { "max_conn": 10 } }"#;
+//! let mut inst = Config::default();
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! let changes = inst.deserialize_over_changes(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(changes, ["limits.max_conn"]);
+//! ```
+//!
+//! Map keys are buffered in order to render them, so tracking changes through a
+//! `HashMap` requires a self-describing format.
+//!
+//! # Error Paths
+//! Errors from deeply nested values don't say which value was at fault.
+//! [`deserialize_over_with_path`](DeserializeOver::deserialize_over_with_path)
+//! prefixes them with the same dotted path that is used for change reporting.
+//! Custom contexts can enable this with [`Context::with_error_paths`] and read
+//! the path from [`Context::error_path`].
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_derive::Deserialize;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(Deserialize, DeserializeOver, Default)]
+//! struct Limit {
+//!     burst: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Config {
+//!     #[deserialize_over]
+//!     per_user: Vec<Limit>,
+//! }
+//!
+//! let json = r#"{ "per_user": [{}, { "burst": "many" }] }"#;
+//! let mut inst = Config::default();
+//! inst.per_user.push(Limit { burst: 1 });
+//! inst.per_user.push(Limit { burst: 2 });
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! let error = inst.deserialize_over_with_path(&mut de).unwrap_err();
+//!
+//! assert!(error.to_string().starts_with("per_user[1].burst: invalid type"));
+//! ```
+//!
+//! # Unknown Keys
+//! Keys which don't match any field are skipped, which makes typos in a patch
+//! easy to miss. Rather than rejecting them outright with
+//! `#[serde(deny_unknown_fields)]`,
+//! [`deserialize_over_unknown_keys`](DeserializeOver::deserialize_over_unknown_keys)
+//! applies the rest of the input and returns every [`UnknownKey`] along with
+//! the closest known field name. Keys are only reported for structs and enum
+//! variants deserialized with `DeserializeOver` that have no
+//! `#[serde(flatten)]` fields, whether they are merged or built from scratch.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Limits {
+//!     max_connection: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Config {
+//!     #[deserialize_over]
+//!     limits: Limits,
+//! }
+//!
+//! let json = r#"{ "limits": { "max_conection": 10 } }"#;
+//! let mut inst = Config::default();
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! let unknown = inst.deserialize_over_unknown_keys(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(unknown[0].path, "limits.max_conection");
+//! assert_eq!(unknown[0].suggestion, Some("max_connection"));
+//! ```
+//!
+//! # Lenient Updates
+//! [`deserialize_over_lenient`](DeserializeOver::deserialize_over_lenient)
+//! applies every field that deserializes successfully and skips the rest,
+//! returning a [`Report`] of the paths that were applied along with the ones
+//! that were rejected and any unknown keys. Each field value is buffered
+//! before it is applied so this requires a self-describing format. Errors in
+//! the input itself, such as malformed JSON, still stop deserialization.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Limits {
+//!     max_conn: u32,
+//!     timeout: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct Config {
+//!     name: String,
+//!     #[deserialize_over]
+//!     limits: Limits,
+//! }
+//!
+//! let json = r#"{ "name": 1, "limits": { "max_conn": 10, "timeout": -1 } }"#;
+//! let mut inst = Config::default();
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! let report = inst.deserialize_over_lenient(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(inst.limits.max_conn, 10);
+//! assert_eq!(report.applied, ["limits.max_conn"]);
+//!
+//! let rejected: Vec<_> = report.rejected.iter().map(|x| &x.path).collect();
+//! assert_eq!(rejected, ["name", "limits.timeout"]);
+//! ```
+//!
+//! # Null Handling
+//! By default `null` is passed on to the field, so a `#[deserialize_over]`
+//! `Option` keeps its value while any other `Option` is set to `None`. This can
+//! be chosen explicitly with `#[deserialize_over(on_null = "...")]`:
+//! - `"keep"`: leave the current value as is.
+//! - `"clear"`: set the field to `None`.
+//! - `"error"`: fail with an error.
+//!
+//! The same attribute on the container sets the default for all of its
+//! `Option` fields. Checking for `null` requires a self-describing format.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_derive::Deserialize;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Deserialize, Default)]
+//! struct Limits {
+//!     max: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct MyStruct {
+//!     #[deserialize_over(on_null = "clear")]
+//!     pub limits: Option<Limits>,
+//! }
+//!
+//! let json = r#"{ "limits": null }"#;
+//! let mut inst = MyStruct { limits: Some(Limits { max: 5 }) };
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_over(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert!(inst.limits.is_none());
+//! ```
+//!
+//! # Merge Patches
+//! [`deserialize_merge_patch`](DeserializeOver::deserialize_merge_patch)
+//! applies the input as a JSON Merge Patch ([RFC 7396]). It behaves like
+//! [`deserialize_over`](DeserializeOver::deserialize_over) except that `null`
+//! removes or resets the corresponding value:
+//! - `#[deserialize_over]` fields of type `Option` are set to `None`.
+//! - Entries of `#[deserialize_over]` maps are removed, as if they were marked
+//!   with `delete_null`.
+//! - Fields with `#[serde(default)]` or `#[serde(default = "...")]` are reset
+//!   to their default.
+//! - Any other field is reset to `Default::default()`, or rejected if its type
+//!   doesn't implement [`Default`]. Fields with `deserialize_with` get the
+//!   `null` passed on as usual.
+//!
+//! Arrays are replaced as a whole as the RFC specifies, rather than merged by
+//! index, unless the field uses `vec = "append"` or `merge_by`. An explicit
+//! `on_null` takes precedence over all of these. Merge patch mode can also be
+//! combined with the other modes through [`Context::with_merge_patch`].
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//! # use serde_json::Deserializer;
+//! # use serde_json::de::StrRead;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! struct MyStruct {
+//!     #[serde(default)]
+//!     pub a: String,
+//!     pub b: Option<i32>,
+//! }
+//!
+//! let json = r#"{ "a": null, "b": null }"#;
+//! let mut inst = MyStruct {
+//!     a: "a string".to_owned(),
+//!     b: Some(5),
+//! };
+//!
+//! let mut de = Deserializer::new(StrRead::new(json));
+//! inst.deserialize_merge_patch(&mut de)
+//!     .expect("Failed to deserialize JSON");
+//!
+//! assert_eq!(inst.a, "");
+//! assert_eq!(inst.b, None);
+//! ```
+//!
+//! [RFC 7396]: https://www.rfc-editor.org/rfc/rfc7396
+//!
+//! # Typed Patches
+//! `#[deserialize_over(patch)]` on a struct with named fields also generates a
+//! `FooPatch` struct where every field is an `Option`, alongside an `apply`
+//! method which writes the fields that are `Some` to a `Foo`. This gives a
+//! typed value for an update that can be built in code, inspected, stored or
+//! sent between threads before it is applied. Patches implement [`Default`],
+//! `Serialize` and [`Deserialize`] using the same field names as `Foo`, and
+//! the fields which are `None` are left out when serializing.
+//!
+//! `#[deserialize_over]` fields whose type is a plain path, such as another
+//! struct, hold a nested patch and require that type to implement
+//! [`Patchable`], either by using `#[deserialize_over(patch)]` as well or by
+//! being a primitive. All other fields are replaced as a whole. A field of type
+//! `Option<T>` becomes `Option<Option<T>>` in the patch, where an explicit
+//! `null` in the input is `Some(None)` and clears the field. Use
+//! `#[deserialize_over(patch = "Name")]` to pick a different name and
+//! `#[deserialize_over(patch_derive = "Clone, Debug")]` to derive extra traits
+//! for the patch.
+//!
+//! ```
+//! use serde_deserialize_over::DeserializeOver;
+//!
+//! #[derive(DeserializeOver, Default)]
+//! #[deserialize_over(patch)]
+//! struct Limits {
+//!     max_conn: u32,
+//!     timeout: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Default)]
+//! #[deserialize_over(patch)]
+//! struct Config {
+//!     name: String,
+//!     #[deserialize_over]
+//!     limits: Limits,
+//! }
+//!
+//! let patch: ConfigPatch = serde_json::from_str(r#"{ "limits": { "timeout": 5 } }"#)
+//!     .expect("Failed to deserialize JSON");
+//! assert!(patch.name.is_none());
+//!
+//! let mut inst = Config::default();
+//! inst.limits.max_conn = 10;
+//! patch.apply(&mut inst);
+//!
+//! assert_eq!(inst.limits.max_conn, 10);
+//! assert_eq!(inst.limits.timeout, 5);
+//! ```
+//!
+//! # Diffs
+//! `#[deserialize_over(diff)]` on a struct with named fields implements
+//! [`Diff`], which computes the changes between an old and a new value.
+//! [`Diff::diff`] returns them as a value that can be passed to any serde
+//! `Serializer`. Deserializing that output over the old value gives the new
+//! one. This is useful for syncing config between nodes or for showing what
+//! an update actually changes.
+//!
+//! Only changed fields are written. Each field is diffed according to how it
+//! is deserialized over:
+//! - `#[deserialize_over]` fields recurse into the diff of their type.
+//! - Sequences merged by index are written up to the last changed element.
+//! - Maps contain only the changed entries. Removed entries are written as
+//!   the tombstone or, with `delete_null`, as `null`.
+//! - Sets contain only the added elements.
+//! - All other fields are written out in full when they differ, using
+//!   `PartialEq` and `Serialize`.
+//!
+//! An option which becomes `None` is written as `null` only if the field
+//! either replaces the option as a whole or has `on_null = "clear"`, since
+//! `null` is ignored otherwise. Changes that can't be expressed are
+//! serialization errors. These include:
+//! - an option that can't be cleared but becomes `None`;
+//! - a map without a tombstone or `delete_null` that lost entries;
+//! - a sequence merged by index that lost elements;
+//! - a set that lost elements;
+//! - an appended sequence whose existing elements changed.
+//!
+//! Skipped fields are never part of the diff, and `flatten`,
+//! `deserialize_with` and `merge_by` fields are not supported.
+//!
+//! ```
+//! use serde_deserialize_over::{DeserializeOver, Diff};
+//!
+//! #[derive(DeserializeOver, Clone)]
+//! #[deserialize_over(diff)]
+//! struct Limits {
+//!     max_conn: u32,
+//!     timeout: u32,
+//! }
+//!
+//! #[derive(DeserializeOver, Clone)]
+//! #[deserialize_over(diff)]
+//! struct Config {
+//!     name: String,
+//!     #[deserialize_over]
+//!     limits: Limits,
+//! }
+//!
+//! let old = Config {
+//!     name: "main".to_owned(),
+//!     limits: Limits { max_conn: 10, timeout: 30 },
+//! };
+//! let mut new = old.clone();
+//! new.limits.timeout = 5;
+//!
+//! let json = serde_json::to_string(&old.diff(&new)).expect("Failed to serialize diff");
+//! assert_eq!(json, r#"{"limits":{"timeout":5}}"#);
+//!
+//! let mut inst = old.clone();
+//! let mut de = serde_json::Deserializer::from_str(&json);
+//! inst.deserialize_over(&mut de).expect("Failed to deserialize JSON");
+//! assert_eq!(inst.limits.timeout, 5);
+//! ```
+//!
+//! # Container Attributes
+//! The derive macro understands the `rename`, `rename_all` and
+//! `deny_unknown_fields` container attributes (plus `rename_all_fields` on
+//! enums) so that it accepts the same keys as the corresponding
+//! [`Deserialize`] impl. Unknown fields are skipped unless
+//! `deny_unknown_fields` is given. Any other container attribute results in a
+//! compile error rather than being silently ignored.
+//!
+//! The `#[deserialize_over(...)]` container attribute accepts `on_null` (see
+//! [Null Handling](#null-handling)), `patch` and `patch_derive` (see
+//! [Typed Patches](#typed-patches)) and `diff` (see [Diffs](#diffs)).
+//!
 //! # Extras
 //! This crate also provides the [`DeserializeInto`] extension trait on all
 //! serde [`Deserializer`]s which takes the operands in the other order.
@@ -106,27 +733,55 @@
 //! [`Deserialize`]: serde::Deserialize
 //! [`Deserializer`]: serde::Deserializer
 
+mod content;
+mod context;
+mod diff;
+mod patch;
 mod support;
 mod tests;
+mod wrapper;
 
 #[doc(hidden)]
 pub mod export {
-  pub use serde::de::{DeserializeSeed, Error, MapAccess, SeqAccess, Unexpected, Visitor};
+  pub use serde::de::{
+    DeserializeSeed, EnumAccess, Error, IgnoredAny, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+  };
+  pub use serde::ser::{Serialize, SerializeStruct, Serializer};
   pub use serde::{Deserialize, Deserializer};
 
+  pub use std::default::Default;
   pub use std::fmt;
   pub use std::marker::PhantomData;
-  pub use std::option::Option::{None, Some};
+  pub use std::option::Option::{self, None, Some};
   pub use std::result::Result::{self, Err, Ok};
+  pub use std::string::String;
+  pub use std::vec::Vec;
 
-  pub use crate::support::{DeserializeOverWrapper, DeserializeWrapper};
-  pub use crate::DeserializeOver;
+  pub use crate::content::{
+    flat_entries_present, take_adjacent_tag, take_internal_tag, Content, ContentDeserializer,
+    FlatMapDeserializer,
+  };
+  pub use crate::diff::ReplaceDiff;
+  pub use crate::support::{
+    deserialize_some, missing_field, AppendDiff, DeserializeOverWrapper, DeserializeWrapper,
+    DiffValue, MapDelete, MapDiff, MapMut, MapWrapper, MergeValues, NullSeed, OptionDiff,
+    RecoverSeed, ReplaceValues, ResetDefault, ResetWithDefault, ResetWithoutDefault, Sequence,
+    VecAppendWrapper, VecMergeByWrapper,
+  };
+  pub use crate::{Context, DeserializeOver, Diff, DiffOf, Patch, Patchable};
 }
 
+pub use crate::context::{Context, Rejected, Report, UnknownKey};
+pub use crate::diff::{Diff, DiffOf};
+pub use crate::patch::{Patch, Patchable};
+pub use crate::wrapper::{Merge, Replace};
 pub use serde_deserialize_over_derive::DeserializeOver;
 
-use crate::support::DeserializeOverWrapper;
-use serde::Deserializer;
+use crate::content::{Content, ContentDeserializer};
+use crate::support::{DeserializeOverWrapper, NullSeed};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
 
 /// Deserialize on top of an existing struct instance.
 ///
@@ -136,6 +791,142 @@ pub trait DeserializeOver<'de> {
   fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
   where
     D: Deserializer<'de>;
+
+  /// Deserialize from `de` on top of this struct instance using the state in
+  /// `cx`.
+  ///
+  /// Implementations should pass `cx` on to any nested values. Those that
+  /// don't override this method cannot take part in a dry run and are reported
+  /// as changed as a whole.
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    if cx.is_dry_run() {
+      return Err(D::Error::custom(format_args!(
+        "{} does not support dry runs",
+        std::any::type_name::<Self>()
+      )));
+    }
+
+    // Without more information any write could have changed the whole value.
+    self.deserialize_over(de)?;
+    cx.record_change();
+    Ok(())
+  }
+
+  /// The names that the field `member` is deserialized from, starting with
+  /// its serialized name and followed by its aliases, if this is a struct with
+  /// such a field.
+  ///
+  /// This is used by `merge_by` to find the key of each element in the input.
+  #[doc(hidden)]
+  fn serialized_field_names(member: &str) -> Option<&'static [&'static str]>
+  where
+    Self: Sized,
+  {
+    let _ = member;
+    None
+  }
+
+  /// Deserialize from `de` on top of this struct instance and return the
+  /// dotted paths of every value that was written.
+  fn deserialize_over_changes<D>(&mut self, de: D) -> Result<Vec<String>, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let mut cx = Context::tracking_changes();
+    self.deserialize_over_with(de, &mut cx)?;
+    Ok(cx.into_changes())
+  }
+
+  /// Apply a JSON Merge Patch ([RFC 7396]) from `de` on top of this struct
+  /// instance.
+  ///
+  /// See the [crate-level documentation](crate#merge-patches) for how `null`
+  /// is handled.
+  ///
+  /// [RFC 7396]: https://www.rfc-editor.org/rfc/rfc7396
+  fn deserialize_merge_patch<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.deserialize_over_with(de, &mut Context::new().with_merge_patch())
+  }
+
+  /// Deserialize from `de` on top of this struct instance, prefixing any error
+  /// with the dotted path of the value that failed to deserialize.
+  ///
+  /// See the [crate-level documentation](crate#error-paths) for details.
+  fn deserialize_over_with_path<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let mut cx = Context::new().with_error_paths();
+    self
+      .deserialize_over_with(de, &mut cx)
+      .map_err(|e| prefix_error_path(&cx, e))
+  }
+
+  /// Deserialize from `de` on top of this struct instance and return the keys
+  /// in the input which did not match any field.
+  ///
+  /// See the [crate-level documentation](crate#unknown-keys) for details.
+  fn deserialize_over_unknown_keys<D>(&mut self, de: D) -> Result<Vec<UnknownKey>, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let mut cx = Context::new().with_unknown_keys();
+    self.deserialize_over_with(de, &mut cx)?;
+    Ok(cx.into_unknown_keys())
+  }
+
+  /// Deserialize from `de` on top of this struct instance, skipping fields
+  /// which fail to deserialize instead of stopping at the first one.
+  ///
+  /// See the [crate-level documentation](crate#lenient-updates) for details.
+  fn deserialize_over_lenient<D>(&mut self, de: D) -> Result<Report, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let mut cx = Context::tracking_changes()
+      .with_collected_errors()
+      .with_unknown_keys();
+    self.deserialize_over_with(de, &mut cx)?;
+    Ok(cx.into_report())
+  }
+
+  /// Deserialize from `de` on top of this struct instance, leaving it
+  /// untouched if an error occurs.
+  ///
+  /// The input is buffered and validated with a dry run before being applied
+  /// so it must come from a self-describing format.
+  fn try_deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let content = Content::deserialize(de)?;
+
+    let mut cx = Context::dry_run().with_error_paths();
+    self
+      .deserialize_over_with(
+        ContentDeserializer::<D::Error>::new(content.clone()),
+        &mut cx,
+      )
+      .map_err(|e| prefix_error_path(&cx, e))?;
+    self.deserialize_over_with(
+      ContentDeserializer::<D::Error>::new(content),
+      &mut Context::new(),
+    )
+  }
+}
+
+/// Prefix `error` with the error path recorded in `cx`, if there is one.
+fn prefix_error_path<E: Error>(cx: &Context, error: E) -> E {
+  match cx.error_path() {
+    Some(path) if !path.is_empty() => E::custom(format_args!("{}: {}", path, error)),
+    _ => error,
+  }
 }
 
 /// Helper trait to allow calling `deserialize_over` on the deserializer itself
@@ -0,0 +1,24 @@
+//! Typed patches generated by `#[deserialize_over(patch)]`.
+
+/// A value which can be applied on top of an existing `T`.
+pub trait Patch<T: ?Sized> {
+  /// Apply this patch to `target`.
+  fn apply(self, target: &mut T);
+}
+
+/// Any value can be used as a patch which replaces a value of the same type.
+impl<T> Patch<T> for T {
+  fn apply(self, target: &mut T) {
+    *target = self;
+  }
+}
+
+/// Types which have a typed patch.
+///
+/// This is implemented by `#[deserialize_over(patch)]` for the struct it is
+/// placed on and for primitives and other leaf types, whose patch is simply a
+/// new value.
+pub trait Patchable {
+  /// The patch type for `Self`.
+  type Patch: Patch<Self>;
+}
@@ -1,11 +1,12 @@
-use crate::{DeserializeOver, DeserializeOverWrapper};
+use crate::{Context, DeserializeOver, DeserializeOverWrapper, Diff};
 use serde::{
   de::{SeqAccess, Visitor},
-  Deserializer,
+  ser::SerializeTuple,
+  Deserializer, Serializer,
 };
 use std::fmt;
 
-struct ArrayVisitor<'a, T, const N: usize>(&'a mut [T; N]);
+struct ArrayVisitor<'a, T, const N: usize>(&'a mut [T; N], &'a mut Context);
 
 impl<'de, 'a, T, const N: usize> Visitor<'de> for ArrayVisitor<'a, T, N>
 where
@@ -21,8 +22,11 @@ where
   where
     A: SeqAccess<'de>,
   {
-    for item in self.0.iter_mut() {
-      if let None = seq.next_element_seed(DeserializeOverWrapper(item))? {
+    for (index, item) in self.0.iter_mut().enumerate() {
+      self.1.push_index(index);
+      let result = seq.next_element_seed(DeserializeOverWrapper(item, &mut *self.1));
+
+      if self.1.pop_result(result)?.is_none() {
         break;
       }
     }
@@ -39,6 +43,43 @@ where
   where
     D: Deserializer<'de>,
   {
-    de.deserialize_tuple(self.len(), ArrayVisitor(self))
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    de.deserialize_tuple(self.len(), ArrayVisitor(self, cx))
+  }
+}
+
+/// Only elements up to the last changed one are written out.
+impl<T, const N: usize> Diff for [T; N]
+where
+  T: Diff,
+{
+  fn differs(&self, new: &Self) -> bool {
+    changed_len(self, new) != 0
+  }
+
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let len = changed_len(self, new);
+    let mut tuple = ser.serialize_tuple(len)?;
+    for (old, new) in self.iter().zip(new).take(len) {
+      tuple.serialize_element(&old.diff(new))?;
+    }
+    tuple.end()
   }
 }
+
+fn changed_len<T: Diff>(old: &[T], new: &[T]) -> usize {
+  old
+    .iter()
+    .zip(new)
+    .rposition(|(old, new)| old.differs(new))
+    .map_or(0, |index| index + 1)
+}
@@ -0,0 +1,86 @@
+use crate::{Context, DeserializeOver, Diff, Patchable};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ffi::OsString;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+  NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+  NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Implements `DeserializeOver`, `Patchable` and `Diff` for types which have no
+/// fields of their own to merge so the whole value is replaced.
+macro_rules! leaf_impl {
+  ( $( $ty:ty ),* $(,)? ) => {
+    $(
+      impl<'de> DeserializeOver<'de> for $ty {
+        fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+        where
+          D: Deserializer<'de>,
+        {
+          self.deserialize_over_with(de, &mut Context::new())
+        }
+
+        fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+        where
+          D: Deserializer<'de>,
+        {
+          if cx.is_dry_run() {
+            return <$ty>::deserialize(de).map(drop);
+          }
+
+          // This allows types such as String to reuse their allocation.
+          <$ty>::deserialize_in_place(de, self)?;
+          cx.record_change();
+          Ok(())
+        }
+      }
+
+      impl Patchable for $ty {
+        type Patch = $ty;
+      }
+
+      impl Diff for $ty {
+        fn differs(&self, new: &Self) -> bool {
+          self != new
+        }
+
+        fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+        where
+          S: Serializer,
+        {
+          new.serialize(ser)
+        }
+      }
+    )*
+  };
+}
+
+leaf_impl!(bool, char, f32, f64);
+leaf_impl!(i8, i16, i32, i64, i128, isize);
+leaf_impl!(u8, u16, u32, u64, u128, usize);
+leaf_impl!(
+  NonZeroI8,
+  NonZeroI16,
+  NonZeroI32,
+  NonZeroI64,
+  NonZeroI128,
+  NonZeroIsize,
+  NonZeroU8,
+  NonZeroU16,
+  NonZeroU32,
+  NonZeroU64,
+  NonZeroU128,
+  NonZeroUsize,
+);
+leaf_impl!(String, OsString, PathBuf);
+leaf_impl!(Duration, SystemTime);
+leaf_impl!(
+  IpAddr,
+  Ipv4Addr,
+  Ipv6Addr,
+  SocketAddr,
+  SocketAddrV4,
+  SocketAddrV6
+);
@@ -1,19 +1,239 @@
-use crate::{DeserializeOver, DeserializeOverWrapper};
-use serde::de::{MapAccess, Visitor};
-use serde::{Deserialize, Deserializer};
+use crate::content::{Content, ContentDeserializer};
+use crate::support::DeserializeWrapper;
+use crate::{Context, DeserializeOver, Diff, NullSeed};
+use serde::de::{DeserializeSeed, MapAccess, Visitor};
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-  collections::{hash_map::Entry, HashMap},
+  collections::{BTreeMap, HashMap},
   fmt,
   hash::{BuildHasher, Hash},
+  marker::PhantomData,
 };
 
-struct MapVisitor<'a, K, V, S>(&'a mut HashMap<K, V, S>);
+/// The operations needed to deserialize over and diff a map.
+#[doc(hidden)]
+pub trait MapMut {
+  type Key;
+  type Value;
 
-impl<'de, 'a, K, V, S> Visitor<'de> for MapVisitor<'a, K, V, S>
+  fn len(&self) -> usize;
+  fn get(&self, key: &Self::Key) -> Option<&Self::Value>;
+  fn iter(&self) -> Box<dyn Iterator<Item = (&Self::Key, &Self::Value)> + '_>;
+  fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value>;
+  fn insert(&mut self, key: Self::Key, value: Self::Value);
+  fn remove(&mut self, key: &Self::Key);
+
+  fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<K, V, S> MapMut for HashMap<K, V, S>
 where
-  K: Deserialize<'de> + Eq + Hash,
-  V: Deserialize<'de> + DeserializeOver<'de>,
+  K: Eq + Hash,
   S: BuildHasher,
+{
+  type Key = K;
+  type Value = V;
+
+  fn len(&self) -> usize {
+    HashMap::len(self)
+  }
+
+  fn get(&self, key: &K) -> Option<&V> {
+    HashMap::get(self, key)
+  }
+
+  fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+    Box::new(HashMap::iter(self))
+  }
+
+  fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+    HashMap::get_mut(self, key)
+  }
+
+  fn insert(&mut self, key: K, value: V) {
+    HashMap::insert(self, key, value);
+  }
+
+  fn remove(&mut self, key: &K) {
+    HashMap::remove(self, key);
+  }
+}
+
+impl<K, V> MapMut for BTreeMap<K, V>
+where
+  K: Ord,
+{
+  type Key = K;
+  type Value = V;
+
+  fn len(&self) -> usize {
+    BTreeMap::len(self)
+  }
+
+  fn get(&self, key: &K) -> Option<&V> {
+    BTreeMap::get(self, key)
+  }
+
+  fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+    Box::new(BTreeMap::iter(self))
+  }
+
+  fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+    BTreeMap::get_mut(self, key)
+  }
+
+  fn insert(&mut self, key: K, value: V) {
+    BTreeMap::insert(self, key, value);
+  }
+
+  fn remove(&mut self, key: &K) {
+    BTreeMap::remove(self, key);
+  }
+}
+
+/// Which values in a patch remove the corresponding map entry.
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MapDelete {
+  /// Remove entries whose value is `null`.
+  pub null: bool,
+  /// Remove entries whose value is this string.
+  pub tombstone: Option<&'static str>,
+}
+
+/// How an existing map value is updated with the value from the input.
+#[doc(hidden)]
+pub trait UpdateValue<'de, V> {
+  fn update<D>(value: &mut V, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>;
+}
+
+/// Deserialize the new value over the existing one.
+#[doc(hidden)]
+pub enum MergeValues {}
+
+/// Replace the existing value with the new one.
+#[doc(hidden)]
+pub enum ReplaceValues {}
+
+impl<'de, V> UpdateValue<'de, V> for MergeValues
+where
+  V: DeserializeOver<'de>,
+{
+  fn update<D>(value: &mut V, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    value.deserialize_over_with(de, cx)
+  }
+}
+
+impl<'de, V> UpdateValue<'de, V> for ReplaceValues
+where
+  V: Deserialize<'de>,
+{
+  fn update<D>(value: &mut V, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    DeserializeWrapper(value, cx).deserialize(de)
+  }
+}
+
+/// A `DeserializeSeed` which updates a single map value using `U`.
+struct UpdateSeed<'a, V, U>(&'a mut V, &'a mut Context, PhantomData<U>);
+
+impl<'a, 'de, V, U> DeserializeSeed<'de> for UpdateSeed<'a, V, U>
+where
+  U: UpdateValue<'de, V>,
+{
+  type Value = ();
+
+  fn deserialize<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    U::update(self.0, de, self.1)
+  }
+}
+
+/// Deserializes over a map, updating existing values using `U` and removing
+/// the entries selected by [`MapDelete`] in addition to those removed by a
+/// merge patch.
+#[doc(hidden)]
+pub struct MapWrapper<'a, M, U = MergeValues>(
+  pub &'a mut M,
+  pub &'a mut Context,
+  pub MapDelete,
+  pub PhantomData<U>,
+);
+
+impl<'a, 'de, M, U> DeserializeSeed<'de> for MapWrapper<'a, M, U>
+where
+  M: MapMut,
+  M::Key: Deserialize<'de>,
+  M::Value: Deserialize<'de>,
+  U: UpdateValue<'de, M::Value>,
+{
+  type Value = ();
+
+  fn deserialize<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let delete = MapDelete {
+      null: self.2.null || self.1.is_merge_patch(),
+      ..self.2
+    };
+
+    de.deserialize_map(MapVisitor(self.0, self.1, delete, PhantomData::<U>))
+  }
+}
+
+/// Deserializes a map value with the inner seed unless it marks the entry for
+/// deletion, in which case `None` is returned.
+struct DeleteSeed<S>(MapDelete, S);
+
+impl<'de, S> DeserializeSeed<'de> for DeleteSeed<S>
+where
+  S: DeserializeSeed<'de>,
+{
+  type Value = Option<S::Value>;
+
+  fn deserialize<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let Self(delete, seed) = self;
+    let tombstone = match delete.tombstone {
+      Some(tombstone) => tombstone,
+      None => return NullSeed(delete.null, seed).deserialize(de),
+    };
+
+    // The value has to be buffered in order to compare it to the tombstone.
+    let content = Content::deserialize(de)?;
+    match content {
+      Content::None | Content::Unit if delete.null => Ok(None),
+      _ if content.as_str() == Some(tombstone) => Ok(None),
+      content => seed
+        .deserialize(ContentDeserializer::<D::Error>::new(content))
+        .map(Some),
+    }
+  }
+}
+
+struct MapVisitor<'a, M, U>(&'a mut M, &'a mut Context, MapDelete, PhantomData<U>);
+
+impl<'de, 'a, M, U> Visitor<'de> for MapVisitor<'a, M, U>
+where
+  M: MapMut,
+  M::Key: Deserialize<'de>,
+  M::Value: Deserialize<'de>,
+  U: UpdateValue<'de, M::Value>,
 {
   type Value = ();
 
@@ -25,21 +245,77 @@ where
   where
     A: MapAccess<'de>,
   {
-    while let Some(key) = map.next_key()? {
-      match self.0.entry(key) {
-        Entry::Occupied(mut entry) => {
-          map.next_value_seed(DeserializeOverWrapper(entry.get_mut()))?
-        }
-        Entry::Vacant(entry) => {
-          entry.insert(map.next_value()?);
-        }
-      }
+    let Self(target, cx, delete, _) = self;
+
+    while let Some((key, label)) = next_key(&mut map, cx)? {
+      cx.push_key(label);
+      let result = match target.get_mut(&key) {
+        Some(value) => map
+          .next_value_seed(DeleteSeed(
+            delete,
+            UpdateSeed::<_, U>(value, &mut *cx, PhantomData),
+          ))
+          .map(|value| {
+            if value.is_none() && !cx.is_dry_run() {
+              target.remove(&key);
+              cx.record_change();
+            }
+          }),
+        None => map
+          .next_value_seed(DeleteSeed(delete, PhantomData::<M::Value>))
+          .map(|value| {
+            if let (Some(value), false) = (value, cx.is_dry_run()) {
+              target.insert(key, value);
+              cx.record_change();
+            }
+          }),
+      };
+      cx.pop_result(result)?;
     }
 
     Ok(())
   }
 }
 
+/// Read the next key along with a label for it.
+///
+/// The label is only needed when tracking the path. In that case the key is
+/// buffered first so that it can be rendered without requiring `K: Display`.
+fn next_key<'de, A, K>(map: &mut A, cx: &Context) -> Result<Option<(K, String)>, A::Error>
+where
+  A: MapAccess<'de>,
+  K: Deserialize<'de>,
+{
+  if !cx.is_tracking_path() {
+    return Ok(map.next_key()?.map(|key| (key, String::new())));
+  }
+
+  match map.next_key::<Content>()? {
+    Some(content) => {
+      let label = content.key_label();
+      let key = K::deserialize(ContentDeserializer::<A::Error>::new(content))?;
+      Ok(Some((key, label)))
+    }
+    None => Ok(None),
+  }
+}
+
+/// Deserialize over `map` using the default behaviour of the map impls.
+fn deserialize_map_over<'de, M, D>(map: &mut M, de: D, cx: &mut Context) -> Result<(), D::Error>
+where
+  M: MapMut,
+  M::Key: Deserialize<'de>,
+  M::Value: Deserialize<'de> + DeserializeOver<'de>,
+  D: Deserializer<'de>,
+{
+  let delete = MapDelete {
+    null: cx.is_merge_patch(),
+    tombstone: None,
+  };
+
+  de.deserialize_map(MapVisitor(map, cx, delete, PhantomData::<MergeValues>))
+}
+
 impl<'de, K, V, S> DeserializeOver<'de> for HashMap<K, V, S>
 where
   K: Deserialize<'de> + Eq + Hash,
@@ -50,6 +326,219 @@ where
   where
     D: Deserializer<'de>,
   {
-    de.deserialize_map(MapVisitor(self))
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    deserialize_map_over(self, de, cx)
+  }
+}
+
+impl<'de, K, V> DeserializeOver<'de> for BTreeMap<K, V>
+where
+  K: Deserialize<'de> + Ord,
+  V: Deserialize<'de> + DeserializeOver<'de>,
+{
+  fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    deserialize_map_over(self, de, cx)
+  }
+}
+
+/// How the diff of a map value is computed, matching [`UpdateValue`].
+#[doc(hidden)]
+pub trait DiffValue<V> {
+  fn differs(old: &V, new: &V) -> bool;
+  fn serialize<S>(old: &V, new: &V, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer;
+}
+
+impl<V> DiffValue<V> for MergeValues
+where
+  V: Diff,
+{
+  fn differs(old: &V, new: &V) -> bool {
+    old.differs(new)
+  }
+
+  fn serialize<S>(old: &V, new: &V, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    old.serialize_diff(new, ser)
+  }
+}
+
+impl<V> DiffValue<V> for ReplaceValues
+where
+  V: PartialEq + Serialize,
+{
+  fn differs(old: &V, new: &V) -> bool {
+    old != new
+  }
+
+  fn serialize<S>(_: &V, new: &V, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    new.serialize(ser)
+  }
+}
+
+/// The diff of a map: changed values are diffed using `U`, added entries are
+/// written out in full and removed entries are written as the tombstone or
+/// `null`, whichever [`MapDelete`] accepts.
+#[doc(hidden)]
+pub struct MapDiff<'a, M, U = MergeValues>(pub &'a M, pub &'a M, pub MapDelete, pub PhantomData<U>);
+
+impl<'a, M, U> MapDiff<'a, M, U>
+where
+  M: MapMut,
+  U: DiffValue<M::Value>,
+{
+  pub fn differs(&self) -> bool {
+    let Self(old, new, ..) = *self;
+
+    old.len() != new.len()
+      || new.iter().any(|(key, new)| match old.get(key) {
+        Some(old) => U::differs(old, new),
+        None => true,
+      })
+  }
+}
+
+/// The diff of a single value, computed using `U`.
+pub(crate) struct ValueDiff<'a, V, U>(pub &'a V, pub &'a V, pub PhantomData<U>);
+
+impl<'a, V, U> Serialize for ValueDiff<'a, V, U>
+where
+  U: DiffValue<V>,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    U::serialize(self.0, self.1, ser)
+  }
+}
+
+/// The diff of a single map entry.
+enum EntryDiff<'a, V, U> {
+  Changed(ValueDiff<'a, V, U>),
+  Added(&'a V),
+  Removed(Option<&'static str>),
+}
+
+impl<'a, V, U> Serialize for EntryDiff<'a, V, U>
+where
+  V: Serialize,
+  U: DiffValue<V>,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    match self {
+      Self::Changed(diff) => diff.serialize(ser),
+      Self::Added(new) => new.serialize(ser),
+      Self::Removed(Some(tombstone)) => ser.serialize_str(tombstone),
+      Self::Removed(None) => ser.serialize_none(),
+    }
+  }
+}
+
+impl<'a, M, U> Serialize for MapDiff<'a, M, U>
+where
+  M: MapMut,
+  M::Key: Serialize,
+  M::Value: Serialize,
+  U: DiffValue<M::Value>,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let Self(old, new, delete, _) = *self;
+    let mut entries = Vec::new();
+
+    for (key, value) in new.iter() {
+      match old.get(key) {
+        Some(old) if U::differs(old, value) => entries.push((
+          key,
+          EntryDiff::Changed(ValueDiff(old, value, PhantomData::<U>)),
+        )),
+        Some(_) => (),
+        None => entries.push((key, EntryDiff::Added(value))),
+      }
+    }
+    for (key, _) in old.iter() {
+      if new.get(key).is_some() {
+        continue;
+      }
+      if !delete.null && delete.tombstone.is_none() {
+        return Err(S::Error::custom(
+          "entries can only be removed from a map with `delete_null` or a tombstone",
+        ));
+      }
+      entries.push((key, EntryDiff::Removed(delete.tombstone)));
+    }
+
+    let mut map = ser.serialize_map(Some(entries.len()))?;
+    for (key, value) in entries {
+      map.serialize_entry(key, &value)?;
+    }
+    map.end()
+  }
+}
+
+/// Changed values are diffed with each other. A plain map only removes entries
+/// in a merge patch so a map which lost entries is an error.
+impl<K, V, S> Diff for HashMap<K, V, S>
+where
+  K: Serialize + Eq + Hash,
+  V: Diff + Serialize,
+  S: BuildHasher,
+{
+  fn differs(&self, new: &Self) -> bool {
+    MapDiff(self, new, MapDelete::default(), PhantomData::<MergeValues>).differs()
+  }
+
+  fn serialize_diff<Ser>(&self, new: &Self, ser: Ser) -> Result<Ser::Ok, Ser::Error>
+  where
+    Ser: Serializer,
+  {
+    MapDiff(self, new, MapDelete::default(), PhantomData::<MergeValues>).serialize(ser)
+  }
+}
+
+/// Changed values are diffed with each other. A plain map only removes entries
+/// in a merge patch so a map which lost entries is an error.
+impl<K, V> Diff for BTreeMap<K, V>
+where
+  K: Serialize + Ord,
+  V: Diff + Serialize,
+{
+  fn differs(&self, new: &Self) -> bool {
+    MapDiff(self, new, MapDelete::default(), PhantomData::<MergeValues>).differs()
+  }
+
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    MapDiff(self, new, MapDelete::default(), PhantomData::<MergeValues>).serialize(ser)
   }
 }
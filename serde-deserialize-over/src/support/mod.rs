@@ -1,15 +1,28 @@
 //! Implementations for types within std
 
 mod array;
+mod leaf;
 mod map;
 mod option;
+mod pointer;
+mod set;
 mod tuple;
+mod vec;
 
-use crate::DeserializeOver;
-use serde::de::{Deserialize, DeserializeSeed, Deserializer};
+pub use self::map::{
+  DiffValue, MapDelete, MapDiff, MapMut, MapWrapper, MergeValues, ReplaceValues,
+};
+pub use self::option::OptionDiff;
+pub use self::vec::{AppendDiff, Sequence, VecAppendWrapper, VecMergeByWrapper};
+
+use crate::content::{Content, ContentDeserializer};
+use crate::{Context, DeserializeOver};
+use serde::de::{Deserialize, DeserializeSeed, Deserializer, Error, Visitor};
+use std::fmt;
+use std::marker::PhantomData;
 
 #[doc(hidden)]
-pub struct DeserializeOverWrapper<'a, T>(pub &'a mut T);
+pub struct DeserializeOverWrapper<'a, T>(pub &'a mut T, pub &'a mut Context);
 
 impl<'a, 'de, T> DeserializeSeed<'de> for DeserializeOverWrapper<'a, T>
 where
@@ -21,11 +34,11 @@ where
   where
     D: Deserializer<'de>,
   {
-    self.0.deserialize_over(de)
+    self.0.deserialize_over_with(de, self.1)
   }
 }
 
-pub struct DeserializeWrapper<'a, T>(pub &'a mut T);
+pub struct DeserializeWrapper<'a, T>(pub &'a mut T, pub &'a mut Context);
 
 impl<'a, 'de, T> DeserializeSeed<'de> for DeserializeWrapper<'a, T>
 where
@@ -37,7 +50,193 @@ where
   where
     D: Deserializer<'de>,
   {
-    *self.0 = T::deserialize(de)?;
+    let value = T::deserialize(de)?;
+    if !self.1.is_dry_run() {
+      *self.0 = value;
+      self.1.record_change();
+    }
+
     Ok(())
   }
 }
+
+/// Resets a value to its `Default` when `null` appears in a merge patch.
+///
+/// The reset is picked by autoref specialization: `reset` on
+/// `&ResetDefault<T>` resolves to [`ResetWithDefault`] when `T: Default` and
+/// to [`ResetWithoutDefault`] otherwise, which gives `None`.
+#[doc(hidden)]
+pub struct ResetDefault<T>(PhantomData<T>);
+
+impl<T> ResetDefault<T> {
+  /// Takes the value only to name its type, which may not be nameable where
+  /// the reset happens.
+  pub fn of(_: &T) -> Self {
+    Self(PhantomData)
+  }
+}
+
+#[doc(hidden)]
+pub trait ResetWithDefault<T> {
+  fn reset(&self) -> Option<T>;
+}
+
+impl<T: Default> ResetWithDefault<T> for ResetDefault<T> {
+  fn reset(&self) -> Option<T> {
+    Some(T::default())
+  }
+}
+
+#[doc(hidden)]
+pub trait ResetWithoutDefault<T> {
+  fn reset(&self) -> Option<T>;
+}
+
+impl<T> ResetWithoutDefault<T> for &ResetDefault<T> {
+  fn reset(&self) -> Option<T> {
+    None
+  }
+}
+
+/// Deserializes with the inner seed unless the input is `null`, in which case
+/// `None` is returned instead.
+///
+/// Only checks for `null` when the flag is set since it requires the
+/// deserializer to support `deserialize_option` for any value.
+#[doc(hidden)]
+pub struct NullSeed<S>(pub bool, pub S);
+
+impl<'de, S> DeserializeSeed<'de> for NullSeed<S>
+where
+  S: DeserializeSeed<'de>,
+{
+  type Value = Option<S::Value>;
+
+  fn deserialize<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    struct NullVisitor<S>(S);
+
+    impl<'de, S> Visitor<'de> for NullVisitor<S>
+    where
+      S: DeserializeSeed<'de>,
+    {
+      type Value = Option<S::Value>;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("any value")
+      }
+
+      fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+      }
+
+      fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+      }
+
+      fn visit_some<D>(self, de: D) -> Result<Self::Value, D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        self.0.deserialize(de).map(Some)
+      }
+    }
+
+    match self.0 {
+      true => de.deserialize_option(NullVisitor(self.1)),
+      false => self.1.deserialize(de).map(Some),
+    }
+  }
+}
+
+/// Deserializes with the inner seed, separating errors in the value itself
+/// from errors in the underlying input.
+///
+/// When the flag is set the value is buffered first so that the deserializer
+/// is left in a consistent state even if the inner seed fails. The message of
+/// that error is then returned as the value so that the caller can record it
+/// and carry on.
+#[doc(hidden)]
+pub struct RecoverSeed<S>(pub bool, pub S);
+
+impl<'de, S> DeserializeSeed<'de> for RecoverSeed<S>
+where
+  S: DeserializeSeed<'de>,
+{
+  type Value = Result<S::Value, String>;
+
+  fn deserialize<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    if !self.0 {
+      return self.1.deserialize(de).map(Ok);
+    }
+
+    let content = Content::deserialize(de)?;
+    Ok(
+      self
+        .1
+        .deserialize(ContentDeserializer::<D::Error>::new(content))
+        .map_err(|e| e.to_string()),
+    )
+  }
+}
+
+/// Deserialize a value which is present in the input, wrapping it in `Some`.
+///
+/// Patch fields for `Option`s use this so that an explicit `null` becomes
+/// `Some(None)` rather than `None`.
+#[doc(hidden)]
+pub fn deserialize_some<'de, T, D>(de: D) -> Result<Option<T>, D::Error>
+where
+  T: Deserialize<'de>,
+  D: Deserializer<'de>,
+{
+  T::deserialize(de).map(Some)
+}
+
+/// Produce the value for a field that was missing when deserializing a fresh
+/// instance of a type.
+///
+/// This mirrors what serde's own derive does: `Option` fields become `None`
+/// and everything else results in a missing field error.
+#[doc(hidden)]
+pub fn missing_field<'de, T, E>(field: &'static str) -> Result<T, E>
+where
+  T: Deserialize<'de>,
+  E: Error,
+{
+  struct MissingFieldDeserializer<E>(&'static str, PhantomData<E>);
+
+  impl<'de, E> Deserializer<'de> for MissingFieldDeserializer<E>
+  where
+    E: Error,
+  {
+    type Error = E;
+
+    fn deserialize_any<V>(self, _: V) -> Result<V::Value, E>
+    where
+      V: Visitor<'de>,
+    {
+      Err(E::missing_field(self.0))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+      V: Visitor<'de>,
+    {
+      visitor.visit_none()
+    }
+
+    serde::forward_to_deserialize_any! {
+      bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+      bytes byte_buf unit unit_struct newtype_struct seq tuple
+      tuple_struct map struct enum identifier ignored_any
+    }
+  }
+
+  T::deserialize(MissingFieldDeserializer(field, PhantomData))
+}
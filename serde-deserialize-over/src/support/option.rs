@@ -1,8 +1,12 @@
-use crate::DeserializeOver;
-use serde::{de::Visitor, Deserialize, Deserializer};
+use crate::support::map::{DiffValue, MergeValues, ValueDiff};
+use crate::{Context, DeserializeOver, Diff};
+use serde::de::{Error, Visitor};
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::marker::PhantomData;
 
-struct OptionVisitor<'a, U>(&'a mut Option<U>);
+struct OptionVisitor<'a, U>(&'a mut Option<U>, &'a mut Context);
 
 impl<'a, 'de, U> Visitor<'de> for OptionVisitor<'a, U>
 where
@@ -19,16 +23,37 @@ where
     D: Deserializer<'de>,
   {
     match self.0 {
-      Some(x) => x.deserialize_over(de)?,
-      None => *self.0 = Some(Deserialize::deserialize(de)?),
+      Some(x) => x.deserialize_over_with(de, self.1)?,
+      None => {
+        let value = Deserialize::deserialize(de)?;
+        if !self.1.is_dry_run() {
+          *self.0 = Some(value);
+          self.1.record_change();
+        }
+      }
     }
 
     Ok(())
   }
 
-  fn visit_none<E>(self) -> Result<(), E> {
+  fn visit_none<E>(self) -> Result<(), E>
+  where
+    E: Error,
+  {
+    if self.1.is_merge_patch() && self.0.is_some() && !self.1.is_dry_run() {
+      *self.0 = None;
+      self.1.record_change();
+    }
+
     Ok(())
   }
+
+  fn visit_unit<E>(self) -> Result<(), E>
+  where
+    E: Error,
+  {
+    self.visit_none()
+  }
 }
 
 impl<'de, T> DeserializeOver<'de> for Option<T>
@@ -39,6 +64,76 @@ where
   where
     D: Deserializer<'de>,
   {
-    de.deserialize_option(OptionVisitor(self))
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    de.deserialize_option(OptionVisitor(self, cx))
+  }
+}
+
+/// The diff of an option field. Two `Some` values are diffed using `U` while a
+/// value which becomes `Some` is written out in full. A value which becomes
+/// `None` is written as `null` if the flag is set, meaning the field is cleared
+/// by `null`, and is an error otherwise.
+#[doc(hidden)]
+pub struct OptionDiff<'a, T, U = MergeValues>(
+  pub &'a Option<T>,
+  pub &'a Option<T>,
+  pub bool,
+  pub PhantomData<U>,
+);
+
+impl<'a, T, U> OptionDiff<'a, T, U>
+where
+  U: DiffValue<T>,
+{
+  pub fn differs(&self) -> bool {
+    match (self.0, self.1) {
+      (Some(old), Some(new)) => U::differs(old, new),
+      (None, None) => false,
+      _ => true,
+    }
+  }
+}
+
+impl<'a, T, U> Serialize for OptionDiff<'a, T, U>
+where
+  T: Serialize,
+  U: DiffValue<T>,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    match (self.0, self.1) {
+      (Some(old), Some(new)) => ser.serialize_some(&ValueDiff(old, new, PhantomData::<U>)),
+      (None, Some(new)) => ser.serialize_some(new),
+      (Some(_), None) if !self.2 => Err(S::Error::custom(
+        "an option can only be cleared with `on_null = \"clear\"`",
+      )),
+      (_, None) => ser.serialize_none(),
+    }
+  }
+}
+
+/// An option which becomes `None` is an error since `null` only clears it in a
+/// merge patch.
+impl<T> Diff for Option<T>
+where
+  T: Diff + Serialize,
+{
+  fn differs(&self, new: &Self) -> bool {
+    OptionDiff(self, new, false, PhantomData::<MergeValues>).differs()
+  }
+
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    OptionDiff(self, new, false, PhantomData::<MergeValues>).serialize(ser)
   }
 }
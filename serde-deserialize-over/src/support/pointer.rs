@@ -0,0 +1,103 @@
+use crate::{Context, DeserializeOver, Diff};
+use serde::de::Error;
+use serde::{Deserializer, Serializer};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Implements `DeserializeOver` for a type by deserializing over the `&mut T`
+/// produced by `$get`.
+macro_rules! forward_impl {
+  ($ty:ident, |$this:ident| $get:expr) => {
+    impl<'de, T> DeserializeOver<'de> for $ty<T>
+    where
+      T: DeserializeOver<'de>,
+    {
+      fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        self.deserialize_over_with(de, &mut Context::new())
+      }
+
+      fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        let $this = self;
+        let target: Result<&mut T, &str> = $get;
+        target
+          .map_err(D::Error::custom)?
+          .deserialize_over_with(de, cx)
+      }
+    }
+  };
+}
+
+forward_impl!(Box, |this| Ok(&mut **this));
+forward_impl!(Cell, |this| Ok(this.get_mut()));
+forward_impl!(RefCell, |this| Ok(this.get_mut()));
+forward_impl!(Mutex, |this| this
+  .get_mut()
+  .map_err(|_| "mutex is poisoned"));
+forward_impl!(RwLock, |this| this
+  .get_mut()
+  .map_err(|_| "rwlock is poisoned"));
+
+/// Implements `DeserializeOver` for a shared pointer by cloning the value if
+/// it is shared with other pointers.
+macro_rules! shared_impl {
+  ($ty:ident) => {
+    impl<'de, T> DeserializeOver<'de> for $ty<T>
+    where
+      T: DeserializeOver<'de> + Clone,
+    {
+      fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        self.deserialize_over_with(de, &mut Context::new())
+      }
+
+      fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        // A dry run must not unshare the value so it works on a copy instead.
+        match $ty::get_mut(self) {
+          Some(value) => value.deserialize_over_with(de, cx),
+          None if cx.is_dry_run() => T::clone(self).deserialize_over_with(de, cx),
+          None => $ty::make_mut(self).deserialize_over_with(de, cx),
+        }
+      }
+    }
+  };
+}
+
+shared_impl!(Rc);
+shared_impl!(Arc);
+
+/// Implements `Diff` for a pointer by diffing the values it points to.
+macro_rules! diff_impl {
+  ($ty:ident) => {
+    impl<T> Diff for $ty<T>
+    where
+      T: Diff + ?Sized,
+    {
+      fn differs(&self, new: &Self) -> bool {
+        T::differs(self, new)
+      }
+
+      fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+      where
+        S: Serializer,
+      {
+        T::serialize_diff(self, new, ser)
+      }
+    }
+  };
+}
+
+diff_impl!(Box);
+diff_impl!(Rc);
+diff_impl!(Arc);
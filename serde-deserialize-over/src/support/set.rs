@@ -0,0 +1,174 @@
+use crate::{Context, DeserializeOver, Diff};
+use serde::de::{SeqAccess, Visitor};
+use serde::ser::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// The operations needed to deserialize over a set.
+trait SetMut {
+  type Item;
+
+  fn insert(&mut self, item: Self::Item) -> bool;
+  fn contains(&self, item: &Self::Item) -> bool;
+}
+
+impl<T, S> SetMut for HashSet<T, S>
+where
+  T: Eq + Hash,
+  S: BuildHasher,
+{
+  type Item = T;
+
+  fn insert(&mut self, item: T) -> bool {
+    HashSet::insert(self, item)
+  }
+
+  fn contains(&self, item: &T) -> bool {
+    HashSet::contains(self, item)
+  }
+}
+
+impl<T> SetMut for BTreeSet<T>
+where
+  T: Ord,
+{
+  type Item = T;
+
+  fn insert(&mut self, item: T) -> bool {
+    BTreeSet::insert(self, item)
+  }
+
+  fn contains(&self, item: &T) -> bool {
+    BTreeSet::contains(self, item)
+  }
+}
+
+struct SetVisitor<'a, C>(&'a mut C, &'a mut Context);
+
+impl<'de, 'a, C> Visitor<'de> for SetVisitor<'a, C>
+where
+  C: SetMut,
+  C::Item: Deserialize<'de>,
+{
+  type Value = ();
+
+  fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    fmt.write_str("a sequence")
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let Self(target, cx) = self;
+    let mut changed = false;
+
+    while let Some(item) = seq.next_element()? {
+      changed |= match cx.is_dry_run() {
+        true => !target.contains(&item),
+        false => target.insert(item),
+      };
+    }
+
+    if changed && !cx.is_dry_run() {
+      cx.record_change();
+    }
+
+    Ok(())
+  }
+}
+
+/// The elements of the input are added to the set.
+impl<'de, T, S> DeserializeOver<'de> for HashSet<T, S>
+where
+  T: Deserialize<'de> + Eq + Hash,
+  S: BuildHasher,
+{
+  fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    de.deserialize_seq(SetVisitor(self, cx))
+  }
+}
+
+/// The elements of the input are added to the set.
+impl<'de, T> DeserializeOver<'de> for BTreeSet<T>
+where
+  T: Deserialize<'de> + Ord,
+{
+  fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    de.deserialize_seq(SetVisitor(self, cx))
+  }
+}
+
+/// Write out the elements of `new` which are missing from `old`.
+///
+/// Elements can't be removed from a set by deserializing over it so a set
+/// which lost elements is an error.
+fn serialize_set_diff<'a, C, S>(old: &'a C, new: &'a C, ser: S) -> Result<S::Ok, S::Error>
+where
+  C: SetMut,
+  &'a C: IntoIterator<Item = &'a C::Item>,
+  C::Item: Serialize + 'a,
+  S: Serializer,
+{
+  if old.into_iter().any(|item| !new.contains(item)) {
+    return Err(S::Error::custom("elements cannot be removed from a set"));
+  }
+
+  let added: Vec<_> = new.into_iter().filter(|item| !old.contains(item)).collect();
+  ser.collect_seq(added)
+}
+
+impl<T, S> Diff for HashSet<T, S>
+where
+  T: Serialize + Eq + Hash,
+  S: BuildHasher,
+{
+  fn differs(&self, new: &Self) -> bool {
+    self != new
+  }
+
+  fn serialize_diff<Ser>(&self, new: &Self, ser: Ser) -> Result<Ser::Ok, Ser::Error>
+  where
+    Ser: Serializer,
+  {
+    serialize_set_diff(self, new, ser)
+  }
+}
+
+impl<T> Diff for BTreeSet<T>
+where
+  T: Serialize + Ord,
+{
+  fn differs(&self, new: &Self) -> bool {
+    self != new
+  }
+
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serialize_set_diff(self, new, ser)
+  }
+}
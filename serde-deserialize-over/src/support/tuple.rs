@@ -1,4 +1,4 @@
-use crate::{DeserializeOver, DeserializeOverWrapper};
+use crate::{Context, DeserializeOver, DeserializeOverWrapper};
 use serde::de::{SeqAccess, Visitor};
 use serde::Deserializer;
 use std::fmt;
@@ -27,6 +27,13 @@ impl<'de> DeserializeOver<'de> for () {
 
     de.deserialize_unit(NoopVisitor)
   }
+
+  fn deserialize_over_with<D>(&mut self, de: D, _: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.deserialize_over(de)
+  }
 }
 
 macro_rules! tuple_impl {
@@ -37,12 +44,19 @@ macro_rules! tuple_impl {
       $( $x: DeserializeOver<'de>, )+
     {
       fn deserialize_over<De>(&mut self, de: De) -> Result<(), De::Error>
+      where
+        De: Deserializer<'de>,
+      {
+        self.deserialize_over_with(de, &mut Context::new())
+      }
+
+      fn deserialize_over_with<De>(&mut self, de: De, cx: &mut Context) -> Result<(), De::Error>
       where
         De: Deserializer<'de>,
       {
         const LEN: usize = 0 $( + if false { stringify!($x).len() } else { 1 } )+;
 
-        struct TupleVisitor<'a, T>(&'a mut T);
+        struct TupleVisitor<'a, T>(&'a mut T, &'a mut Context);
 
         impl<'a, 'de, $( $x, )+> Visitor<'de> for TupleVisitor<'a, ($( $x, )+)>
         where
@@ -54,23 +68,29 @@ macro_rules! tuple_impl {
             fmt.write_fmt(format_args!("a tuple of length {}", LEN))
           }
 
+          #[allow(unused_assignments)]
           fn visit_seq<Ac>(self, mut seq: Ac) -> Result<Self::Value, Ac::Error>
           where
             Ac: SeqAccess<'de>
           {
             let ($( $x, )+) = self.0;
+            let mut index = 0;
 
             $(
-              if seq.next_element_seed(DeserializeOverWrapper($x))?.is_none() {
+              self.1.push_index(index);
+              let result = seq.next_element_seed(DeserializeOverWrapper($x, &mut *self.1));
+
+              if self.1.pop_result(result)?.is_none() {
                 return Ok(());
               }
+              index += 1;
             )+
 
             Ok(())
           }
         }
 
-        de.deserialize_tuple(LEN, TupleVisitor(self))
+        de.deserialize_tuple(LEN, TupleVisitor(self, cx))
       }
     }
   }
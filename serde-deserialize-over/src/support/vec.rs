@@ -0,0 +1,562 @@
+use crate::content::{Content, ContentDeserializer};
+use crate::support::DeserializeWrapper;
+use crate::{Context, DeserializeOver, DeserializeOverWrapper, Diff};
+use serde::de::{DeserializeSeed, Error, SeqAccess, Visitor};
+use serde::ser::{Error as _, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{LinkedList, VecDeque};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The operations needed to deserialize over and diff a sequence.
+#[doc(hidden)]
+pub trait Sequence {
+  type Item;
+
+  fn len(&self) -> usize;
+  fn get(&self, index: usize) -> Option<&Self::Item>;
+  fn get_mut(&mut self, index: usize) -> Option<&mut Self::Item>;
+  fn position<F>(&self, f: F) -> Option<usize>
+  where
+    F: FnMut(&Self::Item) -> bool;
+  fn push(&mut self, item: Self::Item);
+  fn remove(&mut self, index: usize);
+
+  fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<T> Sequence for Vec<T> {
+  type Item = T;
+
+  fn len(&self) -> usize {
+    Vec::len(self)
+  }
+
+  fn get(&self, index: usize) -> Option<&T> {
+    <[T]>::get(self, index)
+  }
+
+  fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+    <[T]>::get_mut(self, index)
+  }
+
+  fn position<F>(&self, f: F) -> Option<usize>
+  where
+    F: FnMut(&T) -> bool,
+  {
+    self.iter().position(f)
+  }
+
+  fn push(&mut self, item: T) {
+    Vec::push(self, item)
+  }
+
+  fn remove(&mut self, index: usize) {
+    Vec::remove(self, index);
+  }
+}
+
+impl<T> Sequence for VecDeque<T> {
+  type Item = T;
+
+  fn len(&self) -> usize {
+    VecDeque::len(self)
+  }
+
+  fn get(&self, index: usize) -> Option<&T> {
+    VecDeque::get(self, index)
+  }
+
+  fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+    VecDeque::get_mut(self, index)
+  }
+
+  fn position<F>(&self, f: F) -> Option<usize>
+  where
+    F: FnMut(&T) -> bool,
+  {
+    self.iter().position(f)
+  }
+
+  fn push(&mut self, item: T) {
+    self.push_back(item)
+  }
+
+  fn remove(&mut self, index: usize) {
+    VecDeque::remove(self, index);
+  }
+}
+
+impl<T> Sequence for LinkedList<T> {
+  type Item = T;
+
+  fn len(&self) -> usize {
+    LinkedList::len(self)
+  }
+
+  fn get(&self, index: usize) -> Option<&T> {
+    self.iter().nth(index)
+  }
+
+  fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+    self.iter_mut().nth(index)
+  }
+
+  fn position<F>(&self, f: F) -> Option<usize>
+  where
+    F: FnMut(&T) -> bool,
+  {
+    self.iter().position(f)
+  }
+
+  fn push(&mut self, item: T) {
+    self.push_back(item)
+  }
+
+  fn remove(&mut self, index: usize) {
+    let mut tail = self.split_off(index);
+    tail.pop_front();
+    self.append(&mut tail);
+  }
+}
+
+struct SeqVisitor<'a, C>(&'a mut C, &'a mut Context);
+
+impl<'de, 'a, C> Visitor<'de> for SeqVisitor<'a, C>
+where
+  C: Sequence,
+  C::Item: Deserialize<'de> + DeserializeOver<'de>,
+{
+  type Value = ();
+
+  fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    fmt.write_str("a sequence")
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let Self(target, cx) = self;
+
+    for index in 0.. {
+      cx.push_index(index);
+      let result = match target.get_mut(index) {
+        Some(item) => seq
+          .next_element_seed(DeserializeOverWrapper(item, &mut *cx))
+          .map(|item| item.is_some()),
+        None => seq.next_element().map(|item| match item {
+          Some(item) => {
+            if !cx.is_dry_run() {
+              target.push(item);
+              cx.record_change();
+            }
+            true
+          }
+          None => false,
+        }),
+      };
+
+      if !cx.pop_result(result)? {
+        break;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Elements are merged by index: element N of the input is deserialized over
+/// element N of the sequence and any extra elements are appended. Elements past
+/// the end of the input are left as is. In a merge patch the sequence is
+/// replaced as a whole instead.
+macro_rules! seq_impl {
+  ($ty:ident) => {
+    impl<'de, T> DeserializeOver<'de> for $ty<T>
+    where
+      T: Deserialize<'de> + DeserializeOver<'de>,
+    {
+      fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        self.deserialize_over_with(de, &mut Context::new())
+      }
+
+      fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        if cx.is_merge_patch() {
+          return DeserializeWrapper(self, cx).deserialize(de);
+        }
+
+        de.deserialize_seq(SeqVisitor(self, cx))
+      }
+    }
+
+    /// Only elements up to the last changed one are written out. Elements can't
+    /// be removed so a shorter sequence is an error.
+    impl<T> Diff for $ty<T>
+    where
+      T: Diff + Serialize,
+    {
+      fn differs(&self, new: &Self) -> bool {
+        self.len() != new.len() || changed_len(self, new) != 0
+      }
+
+      fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+      where
+        S: Serializer,
+      {
+        serialize_seq_diff(self, new, ser)
+      }
+    }
+  };
+}
+
+/// The number of elements of `new` needed to turn `old` into it when merging
+/// by index.
+fn changed_len<C>(old: &C, new: &C) -> usize
+where
+  C: Sequence,
+  C::Item: Diff,
+{
+  if new.len() > old.len() {
+    return new.len();
+  }
+
+  (0..new.len())
+    .rev()
+    .find(|&index| match (old.get(index), new.get(index)) {
+      (Some(old), Some(new)) => old.differs(new),
+      _ => true,
+    })
+    .map_or(0, |index| index + 1)
+}
+
+fn serialize_seq_diff<C, S>(old: &C, new: &C, ser: S) -> Result<S::Ok, S::Error>
+where
+  C: Sequence,
+  C::Item: Diff + Serialize,
+  S: Serializer,
+{
+  if new.len() < old.len() {
+    return Err(S::Error::custom(
+      "elements cannot be removed from a sequence merged by index",
+    ));
+  }
+
+  let len = changed_len(old, new);
+  let mut seq = ser.serialize_seq(Some(len))?;
+  for index in 0..len {
+    match (old.get(index), new.get(index)) {
+      (Some(old), Some(new)) => seq.serialize_element(&old.diff(new))?,
+      (None, Some(new)) => seq.serialize_element(new)?,
+      _ => unreachable!("index is within the bounds of the new sequence"),
+    }
+  }
+  seq.end()
+}
+
+seq_impl!(Vec);
+seq_impl!(VecDeque);
+seq_impl!(LinkedList);
+
+/// Appends all the deserialized elements to the end of a sequence.
+#[doc(hidden)]
+pub struct VecAppendWrapper<'a, C>(pub &'a mut C, pub &'a mut Context);
+
+impl<'a, 'de, C> DeserializeSeed<'de> for VecAppendWrapper<'a, C>
+where
+  C: Sequence + Deserialize<'de> + IntoIterator<Item = <C as Sequence>::Item>,
+{
+  type Value = ();
+
+  fn deserialize<D>(self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let values = C::deserialize(de)?;
+    if self.1.is_dry_run() {
+      return Ok(());
+    }
+
+    for value in values {
+      self.1.push_index(self.0.len());
+      self.0.push(value);
+      self.1.record_change();
+      self.1.pop();
+    }
+
+    Ok(())
+  }
+}
+
+/// The diff of a sequence whose new elements are appended: the elements past
+/// the end of the old sequence, which has to be a prefix of the new one.
+#[doc(hidden)]
+pub struct AppendDiff<'a, C>(pub &'a C, pub &'a C);
+
+impl<'a, C> AppendDiff<'a, C>
+where
+  C: Sequence,
+  C::Item: PartialEq,
+{
+  pub fn differs(&self) -> bool {
+    let Self(old, new) = *self;
+    old.len() != new.len() || !is_prefix(old, new)
+  }
+}
+
+/// Whether `old` is a prefix of `new`.
+fn is_prefix<C>(old: &C, new: &C) -> bool
+where
+  C: Sequence,
+  C::Item: PartialEq,
+{
+  old.len() <= new.len() && (0..old.len()).all(|index| old.get(index) == new.get(index))
+}
+
+impl<'a, C> Serialize for AppendDiff<'a, C>
+where
+  C: Sequence,
+  C::Item: PartialEq + Serialize,
+{
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let Self(old, new) = *self;
+    if !is_prefix(old, new) {
+      return Err(S::Error::custom(
+        "elements can only be appended to this sequence",
+      ));
+    }
+
+    let mut seq = ser.serialize_seq(Some(new.len() - old.len()))?;
+    for index in old.len()..new.len() {
+      seq.serialize_element(new.get(index).expect("index is within bounds"))?;
+    }
+    seq.end()
+  }
+}
+
+/// Merges the elements of a sequence with the incoming elements that have the
+/// same key.
+///
+/// Incoming elements are buffered so that their key can be looked up before
+/// deciding what to do with them. Unmatched elements are appended and, if
+/// `tombstone` is set, elements with that key set to `true` are removed.
+///
+/// In a dry run the target is left alone and the elements that would have been
+/// removed or appended are tracked separately, so that later elements with the
+/// same key are checked against what the real run would see.
+#[doc(hidden)]
+pub struct VecMergeByWrapper<'a, C: Sequence, K, F> {
+  target: &'a mut C,
+  cx: &'a mut Context,
+  key: &'static str,
+  aliases: &'static [&'static str],
+  tombstone: Option<&'static str>,
+  get_key: F,
+  removed: Vec<usize>,
+  added: Vec<C::Item>,
+  _marker: PhantomData<fn() -> K>,
+}
+
+/// Where an incoming element matched during a merge.
+enum Found {
+  Target(usize),
+  Added(usize),
+}
+
+impl<'a, C, K, F> VecMergeByWrapper<'a, C, K, F>
+where
+  C: Sequence,
+  F: Fn(&C::Item) -> &K,
+{
+  pub fn new(
+    target: &'a mut C,
+    cx: &'a mut Context,
+    key: &'static str,
+    tombstone: Option<&'static str>,
+    get_key: F,
+  ) -> Self {
+    Self {
+      target,
+      cx,
+      key,
+      aliases: &[],
+      tombstone,
+      get_key,
+      removed: Vec::new(),
+      added: Vec::new(),
+      _marker: PhantomData,
+    }
+  }
+
+  /// Whether `name` in the input refers to the key field.
+  fn is_key(&self, name: &Content) -> bool {
+    matches!(name.as_str(), Some(name) if name == self.key || self.aliases.contains(&name))
+  }
+
+  /// Find the element with `key`, skipping those removed in a dry run.
+  fn find(&self, key: &K) -> Option<Found>
+  where
+    K: PartialEq,
+  {
+    let get_key = &self.get_key;
+    let target = &*self.target;
+
+    (0..target.len())
+      .find(|index| {
+        !self.removed.contains(index)
+          && matches!(target.get(*index), Some(item) if get_key(item) == key)
+      })
+      .map(Found::Target)
+      .or_else(|| {
+        self
+          .added
+          .iter()
+          .position(|item| get_key(item) == key)
+          .map(Found::Added)
+      })
+  }
+
+  fn merge<'de, E>(&mut self, element: Content<'de>) -> Result<(), E>
+  where
+    C::Item: Deserialize<'de> + DeserializeOver<'de>,
+    K: Deserialize<'de> + PartialEq,
+    E: Error,
+  {
+    let mut entries = match element {
+      Content::Map(entries) => entries,
+      other => return Err(E::invalid_type(other.unexpected(), &"a map")),
+    };
+
+    let label;
+    let key = match entries.iter().find(|(k, _)| self.is_key(k)) {
+      Some((_, value)) => {
+        label = value.key_label();
+        K::deserialize(ContentDeserializer::<E>::new(value.clone()))?
+      }
+      None => return Err(E::missing_field(self.key)),
+    };
+
+    let mut remove = false;
+    if let Some(tombstone) = self.tombstone {
+      if let Some(index) = entries
+        .iter()
+        .position(|(k, _)| k.as_str() == Some(tombstone))
+      {
+        let (_, value) = entries.remove(index);
+        remove = bool::deserialize(ContentDeserializer::<E>::new(value))?;
+      }
+    }
+
+    let found = self.find(&key);
+    if found.is_some() {
+      // The key is already equal so there is no need to write it again.
+      entries.retain(|(k, _)| !self.is_key(k));
+    }
+    let content = ContentDeserializer::<E>::new(Content::Map(entries));
+    let target = &mut *self.target;
+    let added = &mut self.added;
+    let cx = &mut *self.cx;
+
+    cx.push_key(label);
+    let result = match (found, remove) {
+      (Some(Found::Target(index)), true) => {
+        match cx.is_dry_run() {
+          true => self.removed.push(index),
+          false => {
+            target.remove(index);
+            cx.record_change();
+          }
+        }
+        Ok(())
+      }
+      (Some(Found::Added(index)), true) => {
+        added.remove(index);
+        Ok(())
+      }
+      (None, true) => Ok(()),
+      (Some(Found::Target(index)), false) => target
+        .get_mut(index)
+        .expect("find returned an index past the end")
+        .deserialize_over_with(content, cx),
+      (Some(Found::Added(index)), false) => {
+        // Elements appended during a dry run aren't part of the target so they
+        // are updated for real.
+        let mut scratch = match cx.is_merge_patch() {
+          true => Context::new().with_merge_patch(),
+          false => Context::new(),
+        };
+        added[index].deserialize_over_with(content, &mut scratch)
+      }
+      (None, false) => C::Item::deserialize(content).map(|value| match cx.is_dry_run() {
+        true => added.push(value),
+        false => {
+          target.push(value);
+          cx.record_change();
+        }
+      }),
+    };
+
+    cx.pop_result(result)
+  }
+}
+
+impl<'a, 'de, C, K, F> DeserializeSeed<'de> for VecMergeByWrapper<'a, C, K, F>
+where
+  C: Sequence,
+  C::Item: Deserialize<'de> + DeserializeOver<'de>,
+  K: Deserialize<'de> + PartialEq,
+  F: Fn(&C::Item) -> &K,
+{
+  type Value = ();
+
+  fn deserialize<D>(mut self, de: D) -> Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    // The key is given as the Rust name of the field, which may have been
+    // renamed or have aliases in the input.
+    if let Some(names) = <C::Item as DeserializeOver<'de>>::serialized_field_names(self.key) {
+      if let Some((key, aliases)) = names.split_first() {
+        self.key = key;
+        self.aliases = aliases;
+      }
+    }
+
+    de.deserialize_seq(self)
+  }
+}
+
+impl<'a, 'de, C, K, F> Visitor<'de> for VecMergeByWrapper<'a, C, K, F>
+where
+  C: Sequence,
+  C::Item: Deserialize<'de> + DeserializeOver<'de>,
+  K: Deserialize<'de> + PartialEq,
+  F: Fn(&C::Item) -> &K,
+{
+  type Value = ();
+
+  fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "a sequence of maps with the key `{}`", self.key)
+  }
+
+  fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    while let Some(element) = seq.next_element::<Content>()? {
+      self.merge(element)?;
+    }
+
+    Ok(())
+  }
+}
@@ -40,3 +40,114 @@ mod combo_rename_and_deserialize {}
 /// }
 /// ```
 mod duplicate_option {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+/// use serde::*;
+///
+/// #[derive(DeserializeOver)]
+/// #[serde(transparent)]
+/// struct UnsupportedContainerOption {
+///   field: ()
+/// }
+/// ```
+mod unsupported_container_option {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+/// use serde::*;
+///
+/// #[derive(DeserializeOver)]
+/// #[serde(rename_all = "Title Case")]
+/// struct UnknownRenameRule {
+///   field: ()
+/// }
+/// ```
+mod unknown_rename_rule {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+/// use serde::*;
+///
+/// #[derive(Default, DeserializeOver)]
+/// struct Inner {
+///   a: u32,
+/// }
+///
+/// #[derive(DeserializeOver)]
+/// #[serde(deny_unknown_fields)]
+/// struct FlattenDenyUnknown {
+///   #[serde(flatten)]
+///   #[deserialize_over]
+///   inner: Inner,
+/// }
+/// ```
+mod flatten_with_deny_unknown_fields {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+///
+/// #[derive(DeserializeOver)]
+/// struct UnknownStrategy {
+///   #[deserialize_over(vec = "prepend")]
+///   field: Vec<u32>,
+/// }
+/// ```
+mod unknown_vec_strategy {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize, DeserializeOver)]
+/// struct Item {
+///   name: String,
+/// }
+///
+/// #[derive(DeserializeOver)]
+/// struct MergeByWithDeleteNull {
+///   #[deserialize_over(merge_by = "name", delete_null)]
+///   field: Vec<Item>,
+/// }
+/// ```
+mod merge_by_with_delete_null {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+///
+/// #[derive(DeserializeOver)]
+/// struct UnknownOnNull {
+///   #[deserialize_over(on_null = "ignore")]
+///   field: Option<()>,
+/// }
+/// ```
+mod unknown_on_null {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+///
+/// #[derive(DeserializeOver)]
+/// struct OnNullClearOnNonOption {
+///   #[deserialize_over(on_null = "clear")]
+///   field: u32,
+/// }
+/// ```
+mod on_null_clear_on_non_option {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+///
+/// #[derive(DeserializeOver)]
+/// #[deserialize_over(patch)]
+/// struct TuplePatch(u32, u32);
+/// ```
+mod patch_on_tuple_struct {}
+
+/// ```compile_fail
+/// use serde_deserialize_over::*;
+///
+/// #[derive(DeserializeOver)]
+/// #[deserialize_over(diff)]
+/// struct TupleDiff(u32, u32);
+/// ```
+mod diff_on_tuple_struct {}
@@ -0,0 +1,151 @@
+//! Wrappers which pick how a value is deserialized over.
+
+use crate::support::DeserializeWrapper;
+use crate::{Context, DeserializeOver, Diff};
+use serde::de::DeserializeSeed;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::{Deref, DerefMut};
+
+/// Always replaces the wrapped value with a freshly deserialized one.
+///
+/// This allows types which only implement [`Deserialize`] to be used where a
+/// `DeserializeOver` type is needed, such as the values of a
+/// `#[deserialize_over]` map.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Replace<T>(pub T);
+
+/// Always deserializes over the wrapped value.
+///
+/// This is the counterpart to [`Replace`] for generic code which needs to be
+/// explicit about merging. Both wrappers deserialize and serialize exactly like
+/// the value they contain.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Merge<T>(pub T);
+
+macro_rules! wrapper_impl {
+  ($name:ident) => {
+    impl<T> $name<T> {
+      /// Unwrap the contained value.
+      pub fn into_inner(self) -> T {
+        self.0
+      }
+    }
+
+    impl<T> From<T> for $name<T> {
+      fn from(value: T) -> Self {
+        Self(value)
+      }
+    }
+
+    impl<T> Deref for $name<T> {
+      type Target = T;
+
+      fn deref(&self) -> &T {
+        &self.0
+      }
+    }
+
+    impl<T> DerefMut for $name<T> {
+      fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+      }
+    }
+
+    impl<'de, T> Deserialize<'de> for $name<T>
+    where
+      T: Deserialize<'de>,
+    {
+      fn deserialize<D>(de: D) -> Result<Self, D::Error>
+      where
+        D: Deserializer<'de>,
+      {
+        T::deserialize(de).map(Self)
+      }
+    }
+
+    impl<T> Serialize for $name<T>
+    where
+      T: Serialize,
+    {
+      fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+      where
+        S: Serializer,
+      {
+        self.0.serialize(ser)
+      }
+    }
+  };
+}
+
+wrapper_impl!(Replace);
+wrapper_impl!(Merge);
+
+impl<'de, T> DeserializeOver<'de> for Replace<T>
+where
+  T: Deserialize<'de>,
+{
+  fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.deserialize_over_with(de, &mut Context::new())
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    DeserializeWrapper(&mut self.0, cx).deserialize(de)
+  }
+}
+
+impl<'de, T> DeserializeOver<'de> for Merge<T>
+where
+  T: DeserializeOver<'de>,
+{
+  fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.0.deserialize_over(de)
+  }
+
+  fn deserialize_over_with<D>(&mut self, de: D, cx: &mut Context) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.0.deserialize_over_with(de, cx)
+  }
+}
+
+impl<T> Diff for Replace<T>
+where
+  T: PartialEq + Serialize,
+{
+  fn differs(&self, new: &Self) -> bool {
+    self.0 != new.0
+  }
+
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    new.0.serialize(ser)
+  }
+}
+
+impl<T> Diff for Merge<T>
+where
+  T: Diff,
+{
+  fn differs(&self, new: &Self) -> bool {
+    self.0.differs(&new.0)
+  }
+
+  fn serialize_diff<S>(&self, new: &Self, ser: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    self.0.serialize_diff(&new.0, ser)
+  }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::changes;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+use std::collections::HashMap;
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limits {
+  max_conn: u32,
+  timeout: u32,
+}
+
+#[derive(Default, DeserializeOver)]
+struct Inner {
+  a: u32,
+  #[serde(rename = "B")]
+  b: u32,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum Backend {
+  Memory,
+  File { path: String, size: u32 },
+}
+
+#[derive(DeserializeOver)]
+struct Config {
+  name: String,
+  #[deserialize_over]
+  inner: Inner,
+  #[deserialize_over]
+  limits: Option<Limits>,
+  #[deserialize_over]
+  users: HashMap<String, Limits>,
+  #[deserialize_over]
+  ports: [Inner; 2],
+  #[deserialize_over]
+  pair: (Limits, Inner),
+  #[deserialize_over]
+  backend: Backend,
+}
+
+fn config() -> Config {
+  let mut users = HashMap::new();
+  users.insert("root".to_owned(), Limits::default());
+
+  Config {
+    name: "a".to_owned(),
+    inner: Inner::default(),
+    limits: None,
+    users,
+    ports: Default::default(),
+    pair: Default::default(),
+    backend: Backend::File {
+      path: "/tmp".to_owned(),
+      size: 1,
+    },
+  }
+}
+
+#[test]
+fn reports_nested_fields() {
+  let mut instance = config();
+
+  let changes = changes(
+    &mut instance,
+    r#"{ "inner": { "B": 4 }, "limits": { "max_conn": 5, "timeout": 1 }, "name": "b" }"#,
+  );
+  assert_eq!(changes, ["inner.B", "limits", "name"]);
+
+  let changes = self::changes(&mut instance, r#"{ "limits": { "max_conn": 6 } }"#);
+  assert_eq!(changes, ["limits.max_conn"]);
+  assert_eq!(instance.limits.unwrap().max_conn, 6);
+}
+
+#[test]
+fn reports_collection_elements() {
+  let mut instance = config();
+
+  let changes = changes(
+    &mut instance,
+    r#"{
+      "users": { "root": { "timeout": 1 }, "guest": { "max_conn": 1, "timeout": 2 } },
+      "ports": [{ "a": 1 }, { "B": 2 }],
+      "pair": [{ "timeout": 3 }, { "a": 1 }]
+    }"#,
+  );
+  assert_eq!(
+    changes,
+    [
+      "users.root.timeout",
+      "users.guest",
+      "ports[0].a",
+      "ports[1].B",
+      "pair[0].timeout",
+      "pair[1].a"
+    ]
+  );
+}
+
+#[test]
+fn reports_enum_variants() {
+  let mut instance = config();
+
+  let changes = changes(&mut instance, r#"{ "backend": { "File": { "size": 2 } } }"#);
+  assert_eq!(changes, ["backend.size"]);
+
+  let changes = self::changes(&mut instance, r#"{ "backend": "Memory" }"#);
+  assert_eq!(changes, ["backend"]);
+  assert_eq!(instance.backend, Backend::Memory);
+}
+
+#[test]
+fn untouched_fields_are_not_reported() {
+  let mut instance = config();
+
+  assert!(changes(&mut instance, "{}").is_empty());
+  assert!(changes(&mut instance, r#"{ "limits": null }"#).is_empty());
+}
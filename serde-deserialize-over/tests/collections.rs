@@ -0,0 +1,109 @@
+mod common;
+
+use common::changes;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+use std::collections::{BTreeMap, BTreeSet, HashSet, LinkedList, VecDeque};
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Server {
+  name: String,
+  port: u16,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over]
+  limits: BTreeMap<String, Server>,
+  #[deserialize_over(replace_values, delete_null)]
+  ports: BTreeMap<String, u16>,
+  #[deserialize_over]
+  allow: HashSet<String>,
+  #[deserialize_over]
+  deny: BTreeSet<u32>,
+  #[deserialize_over(set = "replace")]
+  admins: BTreeSet<String>,
+  #[deserialize_over]
+  queue: VecDeque<Server>,
+  #[deserialize_over(merge_by = "name")]
+  list: LinkedList<Server>,
+  #[deserialize_over(vec = "append")]
+  log: VecDeque<String>,
+}
+
+fn server(name: &str, port: u16) -> Server {
+  Server {
+    name: name.to_owned(),
+    port,
+  }
+}
+
+#[test]
+fn btree_maps() {
+  let mut instance = Config::default();
+  instance.limits.insert("a".to_owned(), server("a", 1));
+  instance.ports.insert("a".to_owned(), 1);
+  instance.ports.insert("b".to_owned(), 2);
+
+  let changes = changes(
+    &mut instance,
+    r#"{
+      "limits": { "a": { "port": 2 }, "b": { "name": "b", "port": 3 } },
+      "ports": { "a": null, "b": 3 }
+    }"#,
+  );
+
+  assert_eq!(instance.limits["a"], server("a", 2));
+  assert_eq!(instance.limits["b"], server("b", 3));
+  assert_eq!(instance.ports.len(), 1);
+  assert_eq!(instance.ports["b"], 3);
+  assert_eq!(changes, ["limits.a.port", "limits.b", "ports.a", "ports.b"]);
+}
+
+#[test]
+fn sets() {
+  let mut instance = Config::default();
+  instance.allow.insert("a".to_owned());
+  instance.deny.insert(1);
+  instance.admins.insert("root".to_owned());
+
+  let changes = changes(
+    &mut instance,
+    r#"{ "allow": ["a", "b"], "deny": [1], "admins": ["alice"] }"#,
+  );
+
+  assert_eq!(instance.allow.len(), 2);
+  assert!(instance.allow.contains("b"));
+  assert_eq!(instance.deny.iter().collect::<Vec<_>>(), [&1]);
+  assert_eq!(instance.admins.iter().collect::<Vec<_>>(), ["alice"]);
+  assert_eq!(changes, ["allow", "admins"]);
+}
+
+#[test]
+fn sequences() {
+  let mut instance = Config::default();
+  instance.queue.push_back(server("a", 1));
+  instance.list.push_back(server("a", 1));
+  instance.list.push_back(server("b", 2));
+  instance.log.push_back("x".to_owned());
+
+  let changes = changes(
+    &mut instance,
+    r#"{
+      "queue": [{ "port": 2 }, { "name": "b", "port": 3 }],
+      "list": [{ "name": "b", "port": 4 }],
+      "log": ["y"]
+    }"#,
+  );
+
+  assert_eq!(instance.queue, [server("a", 2), server("b", 3)]);
+  assert_eq!(
+    instance.list.iter().cloned().collect::<Vec<_>>(),
+    [server("a", 1), server("b", 4)]
+  );
+  assert_eq!(instance.log, ["x", "y"]);
+  assert_eq!(
+    changes,
+    ["queue[0].port", "queue[1]", "list.b.port", "log[1]"]
+  );
+}
@@ -0,0 +1,30 @@
+//! Helpers shared by the integration tests. Each test only uses some of them.
+#![allow(dead_code)]
+
+use serde_deserialize_over::DeserializeOver;
+
+/// Deserialize `json` over `target`.
+pub fn apply<T>(target: &mut T, json: &str) -> serde_json::Result<()>
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target.deserialize_over(&mut de)
+}
+
+/// Deserialize `json` over `target` and return the paths that were written.
+pub fn apply_changes<T>(target: &mut T, json: &str) -> serde_json::Result<Vec<String>>
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target.deserialize_over_changes(&mut de)
+}
+
+/// Like [`apply_changes`] but panics if `json` can't be deserialized.
+pub fn changes<T>(target: &mut T, json: &str) -> Vec<String>
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  apply_changes(target, json).expect("Failed to deserialize")
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::apply;
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Config {
+  #[serde(alias = "addr", alias = "listen_address")]
+  address: String,
+  #[serde(rename = "max", alias = "max_connections")]
+  limit: u32,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum Backend {
+  #[serde(alias = "InMemory")]
+  Memory,
+  File(String),
+}
+
+#[test]
+fn aliases_are_accepted() {
+  let mut instance = Config::default();
+
+  apply(&mut instance, r#"{ "addr": "a", "max_connections": 1 }"#).expect("Failed to deserialize");
+  apply(&mut instance, r#"{ "listen_address": "b" }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Config {
+      address: "b".to_owned(),
+      limit: 1,
+    }
+  );
+
+  apply(&mut instance, r#"{ "address": "c", "max": 2 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.address, "c");
+  assert_eq!(instance.limit, 2);
+}
+
+#[test]
+fn name_and_alias_is_duplicate() {
+  let mut instance = Config::default();
+
+  let err = apply(&mut instance, r#"{ "address": "a", "addr": "b" }"#)
+    .expect_err("Deserialized a field given twice");
+  assert!(
+    err.to_string().contains("duplicate field `address`"),
+    "{}",
+    err
+  );
+  apply(&mut instance, r#"{ "addr": "a", "listen_address": "b" }"#)
+    .expect_err("Deserialized a field given twice");
+}
+
+#[test]
+fn variant_aliases() {
+  let mut instance = Backend::File("a".to_owned());
+
+  apply(&mut instance, r#""InMemory""#).expect("Failed to deserialize");
+  assert_eq!(instance, Backend::Memory);
+}
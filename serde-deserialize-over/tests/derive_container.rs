@@ -0,0 +1,85 @@
+mod common;
+
+use common::apply;
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", rename = "Config")]
+struct Renamed {
+  max_connections: u32,
+  #[serde(rename = "TTL")]
+  time_to_live: u32,
+  r#type: String,
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Strict {
+  a: i32,
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Lenient {
+  a: i32,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE", rename_all_fields = "PascalCase")]
+enum Mode {
+  ReadOnly,
+  ReadWrite { buffer_size: u32 },
+}
+
+#[test]
+fn rename_all_fields() {
+  let mut instance = Renamed::default();
+
+  apply(
+    &mut instance,
+    r#"{ "maxConnections": 4, "TTL": 60, "type": "x" }"#,
+  )
+  .expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Renamed {
+      max_connections: 4,
+      time_to_live: 60,
+      r#type: "x".to_owned(),
+    }
+  );
+
+  apply(&mut instance, r#"{ "max_connections": 8 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.max_connections, 4);
+}
+
+#[test]
+fn rename_all_variants() {
+  let mut instance = Mode::ReadOnly;
+
+  apply(&mut instance, r#"{ "READ-WRITE": { "BufferSize": 16 } }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Mode::ReadWrite { buffer_size: 16 });
+
+  apply(&mut instance, r#""READ-ONLY""#).expect("Failed to deserialize");
+  assert_eq!(instance, Mode::ReadOnly);
+
+  apply(&mut instance, r#""ReadOnly""#).expect_err("Deserialized the original variant name");
+}
+
+#[test]
+fn deny_unknown_fields() {
+  let mut instance = Strict { a: 1 };
+
+  apply(&mut instance, r#"{ "a": 2 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.a, 2);
+
+  let err = apply(&mut instance, r#"{ "b": 3 }"#).expect_err("Deserialized an unknown field");
+  assert!(err.to_string().contains("unknown field `b`"), "{}", err);
+}
+
+#[test]
+fn unknown_fields_are_skipped() {
+  let mut instance = Lenient { a: 1 };
+
+  apply(&mut instance, r#"{ "b": { "nested": [1, 2] }, "a": 2 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.a, 2);
+}
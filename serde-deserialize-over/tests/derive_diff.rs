@@ -0,0 +1,209 @@
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_deserialize_over::{DeserializeOver, Diff};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Deserialize, DeserializeOver, Serialize, Debug, PartialEq)]
+#[deserialize_over(diff)]
+struct Limits {
+  max_conn: u32,
+  timeout: u32,
+}
+
+#[derive(Clone, DeserializeOver, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[deserialize_over(diff)]
+struct Config {
+  server_name: String,
+  #[deserialize_over]
+  limits: Limits,
+  #[deserialize_over]
+  backends: Vec<Limits>,
+  #[deserialize_over]
+  fallback: Option<Limits>,
+  #[deserialize_over(on_null = "clear")]
+  backup: Option<Limits>,
+  #[deserialize_over(tombstone = "-")]
+  users: BTreeMap<String, Limits>,
+  #[deserialize_over(delete_null)]
+  groups: BTreeMap<String, Limits>,
+  #[deserialize_over]
+  routes: BTreeMap<String, Limits>,
+  #[deserialize_over]
+  tags: BTreeSet<String>,
+  #[deserialize_over(vec = "append")]
+  log: Vec<String>,
+  #[serde(skip)]
+  generation: u32,
+}
+
+fn limits(max_conn: u32, timeout: u32) -> Limits {
+  Limits { max_conn, timeout }
+}
+
+fn config() -> Config {
+  Config {
+    server_name: "main".to_owned(),
+    limits: limits(10, 30),
+    backends: vec![limits(1, 1), limits(2, 2)],
+    fallback: None,
+    backup: Some(limits(3, 3)),
+    users: vec![("root".to_owned(), limits(5, 5))]
+      .into_iter()
+      .collect(),
+    groups: vec![("admin".to_owned(), limits(9, 9))]
+      .into_iter()
+      .collect(),
+    routes: vec![("/".to_owned(), limits(8, 8))].into_iter().collect(),
+    tags: vec!["a".to_owned()].into_iter().collect(),
+    log: vec!["start".to_owned()],
+    generation: 1,
+  }
+}
+
+/// Diff `old` and `new`, check that applying the diff to `old` gives `new`
+/// and return the diff.
+fn roundtrip(old: &Config, new: &Config) -> serde_json::Value {
+  let diff = serde_json::to_value(old.diff(new)).unwrap();
+
+  let mut target = old.clone();
+  target.deserialize_over(&diff).unwrap();
+  assert_eq!(&target, new);
+
+  diff
+}
+
+#[test]
+fn diff_contains_only_changed_fields() {
+  let old = config();
+  let mut new = config();
+  new.limits.timeout = 60;
+  new.backends[1].max_conn = 3;
+
+  assert!(old.differs(&new));
+  assert_eq!(
+    roundtrip(&old, &new),
+    serde_json::json!({
+      "limits": { "timeout": 60 },
+      "backends": [{}, { "max_conn": 3 }],
+    })
+  );
+
+  // Skipped fields are not part of the diff.
+  let mut same = config();
+  same.generation = 2;
+  assert!(!old.differs(&same));
+  same.generation = 1;
+  assert_eq!(roundtrip(&old, &same), serde_json::json!({}));
+}
+
+#[test]
+fn diff_adds_and_removes_entries() {
+  let old = config();
+  let mut new = config();
+  new.server_name = "backup".to_owned();
+  new.backends.push(limits(4, 4));
+  new.fallback = Some(limits(7, 7));
+  new.users.remove("root");
+  new.users.insert("guest".to_owned(), limits(1, 2));
+  new.tags.insert("b".to_owned());
+  new.log.push("reload".to_owned());
+
+  assert_eq!(
+    roundtrip(&old, &new),
+    serde_json::json!({
+      "serverName": "backup",
+      "backends": [{}, {}, { "max_conn": 4, "timeout": 4 }],
+      "fallback": { "max_conn": 7, "timeout": 7 },
+      "users": { "guest": { "max_conn": 1, "timeout": 2 }, "root": "-" },
+      "tags": ["b"],
+      "log": ["reload"],
+    })
+  );
+}
+
+#[test]
+fn removals_use_null_where_accepted() {
+  let old = config();
+  let mut new = config();
+  new.backup = None;
+  new.groups.clear();
+
+  assert_eq!(
+    roundtrip(&old, &new),
+    serde_json::json!({ "backup": null, "groups": { "admin": null } })
+  );
+}
+
+#[test]
+fn inexpressible_changes_are_errors() {
+  let old = config();
+
+  let mut new = config();
+  new.backends.pop();
+  assert!(serde_json::to_value(old.diff(&new)).is_err());
+
+  let mut new = config();
+  new.log = vec!["other".to_owned()];
+  assert!(serde_json::to_value(old.diff(&new)).is_err());
+
+  let mut new = config();
+  new.tags.clear();
+  assert!(serde_json::to_value(old.diff(&new)).is_err());
+
+  // `null` is ignored by a merged option and rejected by a plain map.
+  let mut old = config();
+  old.fallback = Some(limits(7, 7));
+  let new = config();
+  assert!(serde_json::to_value(old.diff(&new)).is_err());
+
+  let old = config();
+  let mut new = config();
+  new.routes.clear();
+  assert!(serde_json::to_value(old.diff(&new)).is_err());
+}
+
+#[derive(Clone, DeserializeOver, Debug, PartialEq)]
+#[deserialize_over(diff)]
+struct Wrapper<T: DeserializeOwned> {
+  #[deserialize_over]
+  inner: T,
+  label: String,
+  #[deserialize_over(vec = "append")]
+  history: Vec<T>,
+  #[deserialize_over(delete_null)]
+  named: BTreeMap<String, T>,
+  #[deserialize_over(on_null = "clear")]
+  extra: Option<T>,
+}
+
+#[test]
+fn diff_generic_struct() {
+  let old = Wrapper {
+    inner: limits(1, 2),
+    label: "a".to_owned(),
+    history: vec![limits(1, 1)],
+    named: vec![("x".to_owned(), limits(2, 2))].into_iter().collect(),
+    extra: Some(limits(3, 3)),
+  };
+  let mut new = old.clone();
+  new.inner.timeout = 3;
+  new.history.push(limits(4, 4));
+  new.named.clear();
+  new.extra = None;
+
+  let diff = serde_json::to_value(old.diff(&new)).unwrap();
+  assert_eq!(
+    diff,
+    serde_json::json!({
+      "inner": { "timeout": 3 },
+      "history": [{ "max_conn": 4, "timeout": 4 }],
+      "named": { "x": null },
+      "extra": null,
+    })
+  );
+
+  let mut target = old.clone();
+  target.deserialize_over(&diff).unwrap();
+  assert_eq!(target, new);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::apply;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Inner {
+  a: i32,
+  b: i32,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum Backend {
+  Memory,
+  File(String),
+  Nested(#[deserialize_over] Inner),
+  Pair(u16, u16),
+  Postgres {
+    host: String,
+    port: u16,
+    user: Option<String>,
+  },
+}
+
+#[derive(DeserializeOver)]
+enum Generic<T> {
+  A(T),
+  B { value: T },
+}
+
+#[test]
+fn same_variant_merges() {
+  let mut instance = Backend::Postgres {
+    host: "localhost".to_owned(),
+    port: 5432,
+    user: None,
+  };
+
+  apply(&mut instance, r#"{ "Postgres": { "port": 6543 } }"#).expect("Failed to deserialize");
+
+  assert_eq!(
+    instance,
+    Backend::Postgres {
+      host: "localhost".to_owned(),
+      port: 6543,
+      user: None,
+    }
+  );
+}
+
+#[test]
+fn different_variant_replaces() {
+  let mut instance = Backend::Memory;
+
+  apply(
+    &mut instance,
+    r#"{ "Postgres": { "host": "db", "port": 1 } }"#,
+  )
+  .expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Backend::Postgres {
+      host: "db".to_owned(),
+      port: 1,
+      user: None,
+    }
+  );
+
+  apply(&mut instance, r#""Memory""#).expect("Failed to deserialize");
+  assert_eq!(instance, Backend::Memory);
+
+  apply(&mut instance, r#"{ "Pair": [1, 2] }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Backend::Pair(1, 2));
+}
+
+#[test]
+fn different_variant_requires_all_fields() {
+  let mut instance = Backend::Memory;
+
+  apply(&mut instance, r#"{ "Postgres": { "port": 1 } }"#)
+    .expect_err("Deserialized a variant with missing fields");
+  apply(&mut instance, r#"{ "Pair": [1] }"#)
+    .expect_err("Deserialized a variant with missing fields");
+}
+
+#[test]
+fn tuple_and_newtype_variants_merge() {
+  let mut instance = Backend::Pair(1, 2);
+  apply(&mut instance, r#"{ "Pair": [3] }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Backend::Pair(3, 2));
+
+  let mut instance = Backend::Nested(Inner { a: 1, b: 2 });
+  apply(&mut instance, r#"{ "Nested": { "b": 5 } }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Backend::Nested(Inner { a: 1, b: 5 }));
+
+  apply(&mut instance, r#"{ "File": "config.toml" }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Backend::File("config.toml".to_owned()));
+}
+
+#[test]
+fn unknown_variant_fails() {
+  let mut instance = Backend::Memory;
+
+  apply(&mut instance, r#"{ "Redis": {} }"#).expect_err("Deserialized an unknown variant");
+  assert_eq!(instance, Backend::Memory);
+}
+
+#[test]
+fn generic() {
+  let mut instance = Generic::<String>::A("a".to_owned());
+  let mut de =
+    serde_json::Deserializer::new(serde_json::de::StrRead::new(r#"{ "B": { "value": "b" } }"#));
+
+  instance
+    .deserialize_over(&mut de)
+    .expect("Failed to deserialize");
+
+  match instance {
+    Generic::B { value } => assert_eq!(value, "b"),
+    Generic::A(_) => panic!("variant was not replaced"),
+  }
+}
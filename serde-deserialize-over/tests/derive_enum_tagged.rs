@@ -0,0 +1,164 @@
+mod common;
+
+use common::apply;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Credentials {
+  user: String,
+  password: String,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(tag = "type")]
+enum Internal {
+  Memory,
+  S3 { bucket: String, region: String },
+  Auth(#[deserialize_over] Credentials),
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(tag = "t", content = "c")]
+enum Adjacent {
+  Memory,
+  File(String),
+  Pair(u16, u16),
+  S3 { bucket: String, region: String },
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(untagged)]
+enum Untagged {
+  Port(u16),
+  Name(String),
+  S3 { bucket: String, region: String },
+}
+
+#[test]
+fn internally_tagged() {
+  let mut instance = Internal::S3 {
+    bucket: "x".to_owned(),
+    region: "us".to_owned(),
+  };
+
+  apply(&mut instance, r#"{ "type": "S3", "region": "eu" }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Internal::S3 {
+      bucket: "x".to_owned(),
+      region: "eu".to_owned(),
+    }
+  );
+
+  apply(&mut instance, r#"{ "type": "Memory" }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Internal::Memory);
+
+  apply(&mut instance, r#"{ "type": "S3", "region": "eu" }"#)
+    .expect_err("Deserialized a variant with missing fields");
+  apply(&mut instance, r#"{ "region": "eu" }"#).expect_err("Deserialized without a tag");
+
+  apply(
+    &mut instance,
+    r#"{ "user": "root", "type": "Auth", "password": "hunter2" }"#,
+  )
+  .expect("Failed to deserialize");
+  apply(&mut instance, r#"{ "type": "Auth", "user": "admin" }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Internal::Auth(Credentials {
+      user: "admin".to_owned(),
+      password: "hunter2".to_owned(),
+    })
+  );
+}
+
+#[test]
+fn adjacently_tagged() {
+  let mut instance = Adjacent::Pair(1, 2);
+
+  apply(&mut instance, r#"{ "t": "Pair", "c": [3] }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Adjacent::Pair(3, 2));
+
+  apply(&mut instance, r#"{ "c": "a.txt", "t": "File" }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Adjacent::File("a.txt".to_owned()));
+
+  apply(&mut instance, r#"{ "t": "Memory" }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Adjacent::Memory);
+
+  apply(
+    &mut instance,
+    r#"{ "t": "S3", "c": { "bucket": "x", "region": "us" } }"#,
+  )
+  .expect("Failed to deserialize");
+  apply(&mut instance, r#"{ "t": "S3", "c": { "region": "eu" } }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Adjacent::S3 {
+      bucket: "x".to_owned(),
+      region: "eu".to_owned(),
+    }
+  );
+
+  apply(&mut instance, r#"{ "t": "File" }"#).expect_err("Deserialized without content");
+}
+
+#[test]
+fn untagged() {
+  let mut instance = Untagged::Port(80);
+
+  apply(&mut instance, r#"8080"#).expect("Failed to deserialize");
+  assert_eq!(instance, Untagged::Port(8080));
+
+  apply(&mut instance, r#""localhost""#).expect("Failed to deserialize");
+  assert_eq!(instance, Untagged::Name("localhost".to_owned()));
+
+  apply(&mut instance, r#"{ "bucket": "x", "region": "us" }"#).expect("Failed to deserialize");
+  apply(&mut instance, r#"{ "region": "eu" }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Untagged::S3 {
+      bucket: "x".to_owned(),
+      region: "eu".to_owned(),
+    }
+  );
+
+  apply(&mut instance, r#"[1, 2]"#).expect_err("Deserialized data matching no variant");
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(untagged)]
+enum Limit {
+  Count { count: u32 },
+  Rate { rate: u32, burst: u32 },
+}
+
+#[test]
+fn untagged_switches_variant() {
+  // Merging would succeed by ignoring `rate` and `burst` so the current
+  // variant is only kept if every key is used.
+  let mut instance = Limit::Count { count: 1 };
+  apply(&mut instance, r#"{ "rate": 5, "burst": 10 }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Limit::Rate { rate: 5, burst: 10 });
+
+  apply(&mut instance, r#"{ "burst": 20 }"#).expect("Failed to deserialize");
+  assert_eq!(instance, Limit::Rate { rate: 5, burst: 20 });
+}
+
+#[test]
+fn failed_untagged_attempt_leaves_value_unchanged() {
+  let mut instance = Untagged::S3 {
+    bucket: "x".to_owned(),
+    region: "us".to_owned(),
+  };
+
+  apply(&mut instance, r#"{ "region": "eu", "bucket": 5 }"#)
+    .expect_err("Deserialized data matching no variant");
+  assert_eq!(
+    instance,
+    Untagged::S3 {
+      bucket: "x".to_owned(),
+      region: "us".to_owned(),
+    }
+  );
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::apply;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Common {
+  verbose: bool,
+  level: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Merged {
+  name: String,
+  #[serde(flatten)]
+  #[deserialize_over]
+  common: Common,
+  #[serde(flatten)]
+  extra: HashMap<String, Value>,
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Replaced {
+  name: String,
+  #[serde(flatten)]
+  common: Common,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum Service {
+  Stopped,
+  Running {
+    pid: u32,
+    #[serde(flatten)]
+    #[deserialize_over]
+    common: Common,
+  },
+}
+
+#[test]
+fn flatten_merges_and_collects_unknown_keys() {
+  let mut instance = Merged {
+    name: "a".to_owned(),
+    common: Common {
+      verbose: true,
+      level: 1,
+    },
+    extra: HashMap::new(),
+  };
+
+  apply(
+    &mut instance,
+    r#"{ "level": 3, "colour": "red", "name": "b" }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(instance.name, "b");
+  assert_eq!(
+    instance.common,
+    Common {
+      verbose: true,
+      level: 3,
+    }
+  );
+  assert_eq!(instance.extra.len(), 1);
+  assert_eq!(instance.extra["colour"], "red");
+}
+
+#[test]
+fn flatten_without_deserialize_over_replaces() {
+  let mut instance = Replaced {
+    name: "a".to_owned(),
+    common: Common {
+      verbose: true,
+      level: 1,
+    },
+  };
+
+  apply(&mut instance, r#"{ "level": 3, "verbose": false }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance.common,
+    Common {
+      verbose: false,
+      level: 3,
+    }
+  );
+
+  apply(&mut instance, r#"{ "level": 4 }"#).expect_err("Deserialized a partial flattened struct");
+}
+
+#[test]
+fn flatten_without_deserialize_over_needs_its_keys() {
+  let mut instance = Merged {
+    name: "a".to_owned(),
+    common: Common::default(),
+    extra: vec![("colour".to_owned(), Value::from("red"))]
+      .into_iter()
+      .collect(),
+  };
+  apply(&mut instance, r#"{ "name": "b" }"#).expect("Failed to deserialize");
+  assert_eq!(instance.extra["colour"], "red");
+
+  let mut instance = Replaced {
+    name: "a".to_owned(),
+    common: Common {
+      verbose: true,
+      level: 1,
+    },
+  };
+  apply(&mut instance, r#"{ "name": "b", "other": 1 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.name, "b");
+  assert_eq!(
+    instance.common,
+    Common {
+      verbose: true,
+      level: 1,
+    }
+  );
+}
+
+#[test]
+fn flatten_in_struct_variant() {
+  let mut instance = Service::Stopped;
+
+  apply(
+    &mut instance,
+    r#"{ "Running": { "pid": 1, "verbose": true, "level": 2 } }"#,
+  )
+  .expect("Failed to deserialize");
+  apply(&mut instance, r#"{ "Running": { "level": 5 } }"#).expect("Failed to deserialize");
+
+  assert_eq!(
+    instance,
+    Service::Running {
+      pid: 1,
+      common: Common {
+        verbose: true,
+        level: 5,
+      },
+    }
+  );
+}
@@ -6,11 +6,13 @@ struct ExampleStruct<T> {
   pub b: i32,
 }
 
+#[allow(dead_code)]
 #[derive(DeserializeOver)]
 struct WithConstraints<T: Default> {
   pub a: T,
 }
 
+#[allow(dead_code)]
 #[derive(DeserializeOver)]
 struct WithConstGenerics<const N: usize> {
   #[deserialize_over]
@@ -0,0 +1,140 @@
+mod common;
+
+use common::apply_changes;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Server {
+  name: String,
+  port: u16,
+}
+
+#[derive(Clone, Deserialize, DeserializeOver, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct User {
+  #[serde(alias = "uid")]
+  user_id: u32,
+  display_name: String,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over(merge_by = "name")]
+  servers: Vec<Server>,
+  #[deserialize_over(merge_by = "name", tombstone = "_delete")]
+  removable: Vec<Server>,
+  #[deserialize_over(merge_by = "user_id")]
+  users: Vec<User>,
+}
+
+fn server(name: &str, port: u16) -> Server {
+  Server {
+    name: name.to_owned(),
+    port,
+  }
+}
+
+fn config() -> Config {
+  let servers = vec![server("a", 80), server("b", 80), server("c", 80)];
+
+  Config {
+    servers: servers.clone(),
+    removable: servers,
+    users: Vec::new(),
+  }
+}
+
+#[test]
+fn merges_matching_elements() {
+  let mut instance = config();
+
+  let changes = apply_changes(
+    &mut instance,
+    r#"{ "servers": [{ "name": "b", "port": 81 }, { "name": "d", "port": 82 }] }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(
+    instance.servers,
+    [
+      server("a", 80),
+      server("b", 81),
+      server("c", 80),
+      server("d", 82)
+    ]
+  );
+  assert_eq!(changes, ["servers.b.port", "servers.d"]);
+}
+
+#[test]
+fn tombstone_removes_elements() {
+  let mut instance = config();
+
+  let changes = apply_changes(
+    &mut instance,
+    r#"{ "removable": [
+      { "name": "a", "_delete": true },
+      { "name": "b", "_delete": false, "port": 81 },
+      { "name": "x", "_delete": true }
+    ] }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(instance.removable, [server("b", 81), server("c", 80)]);
+  assert_eq!(changes, ["removable.a", "removable.b.port"]);
+}
+
+#[test]
+fn elements_without_key_fail() {
+  let mut instance = config();
+
+  let err = apply_changes(&mut instance, r#"{ "servers": [{ "port": 81 }] }"#)
+    .expect_err("Deserialized an element without a key");
+  assert!(err.to_string().contains("missing field `name`"), "{}", err);
+
+  apply_changes(
+    &mut instance,
+    r#"{ "servers": [{ "name": "a", "_delete": true }] }"#,
+  )
+  .expect_err("Tombstone was accepted without being enabled");
+}
+
+#[test]
+fn key_uses_the_serialized_field_name() {
+  let mut instance = config();
+  instance.users.push(User {
+    user_id: 1,
+    display_name: "a".to_owned(),
+  });
+
+  let changes = apply_changes(
+    &mut instance,
+    r#"{ "users": [{ "userId": 1, "displayName": "b" }, { "userId": 2, "displayName": "c" }] }"#,
+  )
+  .expect("Failed to deserialize");
+
+  let names: Vec<_> = instance.users.iter().map(|x| &x.display_name[..]).collect();
+  assert_eq!(names, ["b", "c"]);
+  assert_eq!(changes, ["users.1.displayName", "users.2"]);
+}
+
+#[test]
+fn key_accepts_aliases() {
+  let mut instance = config();
+  instance.users.push(User {
+    user_id: 1,
+    display_name: "a".to_owned(),
+  });
+
+  let changes = apply_changes(
+    &mut instance,
+    r#"{ "users": [{ "uid": 1, "displayName": "b" }] }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(instance.users.len(), 1);
+  assert_eq!(instance.users[0].display_name, "b");
+  assert_eq!(changes, ["users.1.displayName"]);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::apply_changes;
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver};
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limits {
+  max: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Fields {
+  #[deserialize_over]
+  default: Option<Limits>,
+  #[deserialize_over(on_null = "keep")]
+  keep: Option<Limits>,
+  #[deserialize_over(on_null = "clear")]
+  clear: Option<Limits>,
+  #[deserialize_over(on_null = "error")]
+  error: Option<Limits>,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+#[deserialize_over(on_null = "keep")]
+struct Container {
+  a: Option<String>,
+  #[deserialize_over(on_null = "clear")]
+  b: Option<Limits>,
+  c: u32,
+}
+
+fn limits(max: u32) -> Option<Limits> {
+  Some(Limits { max })
+}
+
+#[test]
+fn field_behaviours() {
+  let mut instance = Fields {
+    default: limits(1),
+    keep: limits(2),
+    clear: limits(3),
+    error: limits(4),
+  };
+
+  let changes = apply_changes(
+    &mut instance,
+    r#"{ "default": null, "keep": null, "clear": null, "error": { "max": 5 } }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(instance.default, limits(1));
+  assert_eq!(instance.keep, limits(2));
+  assert_eq!(instance.clear, None);
+  assert_eq!(instance.error, limits(5));
+  assert_eq!(changes, ["clear", "error.max"]);
+
+  let err = apply_changes(&mut instance, r#"{ "error": null }"#).expect_err("Accepted null");
+  assert!(
+    err.to_string().contains("field `error` cannot be null"),
+    "{}",
+    err
+  );
+}
+
+#[test]
+fn container_default() {
+  let mut instance = Container {
+    a: Some("a".to_owned()),
+    b: limits(1),
+    c: 2,
+  };
+
+  apply_changes(&mut instance, r#"{ "a": null, "b": null }"#).expect("Failed to deserialize");
+
+  assert_eq!(instance.a.as_deref(), Some("a"));
+  assert_eq!(instance.b, None);
+
+  apply_changes(&mut instance, r#"{ "c": null }"#).expect_err("Deserialized null as a number");
+}
+
+#[test]
+fn error_in_dry_run() {
+  let mut instance = Fields {
+    error: limits(4),
+    ..Default::default()
+  };
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(r#"{ "error": null }"#));
+  instance
+    .deserialize_over_with(&mut de, &mut Context::dry_run())
+    .expect_err("Accepted null");
+  assert_eq!(instance.error, limits(4));
+}
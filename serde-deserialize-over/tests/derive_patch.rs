@@ -0,0 +1,149 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_deserialize_over::{DeserializeOver, Patch};
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Serialize, Debug, PartialEq)]
+#[deserialize_over(patch, patch_derive = "Clone, Debug, PartialEq")]
+struct Limits {
+  max_conn: u32,
+  timeout: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[deserialize_over(patch = "ConfigOverlay", patch_derive = "Debug")]
+struct Config {
+  server_name: String,
+  #[deserialize_over]
+  limits: Limits,
+  #[deserialize_over]
+  ports: Vec<u16>,
+  motd: Option<String>,
+  #[serde(skip)]
+  generation: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+#[deserialize_over(patch)]
+struct Wrapper<T> {
+  #[deserialize_over]
+  inner: T,
+  label: String,
+}
+
+fn config() -> Config {
+  Config {
+    server_name: "main".to_owned(),
+    limits: Limits {
+      max_conn: 10,
+      timeout: 30,
+    },
+    ports: vec![80, 443],
+    motd: Some("hello".to_owned()),
+    generation: 1,
+  }
+}
+
+#[test]
+fn apply_patch_built_in_code() {
+  let mut config = config();
+  let patch = ConfigOverlay {
+    server_name: None,
+    limits: Some(LimitsPatch {
+      max_conn: Some(20),
+      timeout: None,
+    }),
+    ports: Some(vec![8080]),
+    motd: None,
+  };
+
+  patch.apply(&mut config);
+
+  assert_eq!(config.server_name, "main");
+  assert_eq!(
+    config.limits,
+    Limits {
+      max_conn: 20,
+      timeout: 30
+    }
+  );
+  assert_eq!(config.ports, [8080]);
+  assert_eq!(config.generation, 1);
+}
+
+#[test]
+fn patch_serde_round_trip() {
+  let json = r#"{ "serverName": "backup", "limits": { "timeout": 5 } }"#;
+  let patch: ConfigOverlay = serde_json::from_str(json).unwrap();
+
+  assert_eq!(patch.server_name.as_deref(), Some("backup"));
+  assert_eq!(
+    patch.limits,
+    Some(LimitsPatch {
+      max_conn: None,
+      timeout: Some(5),
+    })
+  );
+  assert_eq!(patch.ports, None);
+  assert_eq!(
+    serde_json::to_string(&patch).unwrap(),
+    r#"{"serverName":"backup","limits":{"timeout":5}}"#
+  );
+
+  // Applying the patch matches deserializing the same input over the value.
+  let mut patched = config();
+  Patch::apply(patch, &mut patched);
+
+  let mut merged = config();
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  merged.deserialize_over(&mut de).unwrap();
+
+  assert_eq!(patched, merged);
+}
+
+#[test]
+fn patch_clears_options_with_null() {
+  let patch: ConfigOverlay = serde_json::from_str(r#"{ "motd": null }"#).unwrap();
+  assert_eq!(patch.motd, Some(None));
+  assert_eq!(serde_json::to_string(&patch).unwrap(), r#"{"motd":null}"#);
+
+  let patch: ConfigOverlay = serde_json::from_str(&serde_json::to_string(&patch).unwrap()).unwrap();
+  let mut config = config();
+  patch.apply(&mut config);
+  assert_eq!(config.motd, None);
+
+  let patch: ConfigOverlay = serde_json::from_str("{}").unwrap();
+  assert_eq!(patch.motd, None);
+  let mut config = self::config();
+  patch.apply(&mut config);
+  assert_eq!(config.motd.as_deref(), Some("hello"));
+}
+
+#[test]
+fn generic_patch_can_be_sent() {
+  fn assert_send<T: Send>(_: &T) {}
+
+  let patch: WrapperPatch<Limits> =
+    serde_json::from_str(r#"{ "inner": { "max_conn": 1 } }"#).unwrap();
+  assert_send(&patch);
+
+  let mut wrapper = Wrapper {
+    inner: Limits {
+      max_conn: 10,
+      timeout: 30,
+    },
+    label: "a".to_owned(),
+  };
+  std::thread::spawn(move || {
+    patch.apply(&mut wrapper);
+    assert_eq!(
+      wrapper.inner,
+      Limits {
+        max_conn: 1,
+        timeout: 30
+      }
+    );
+    assert_eq!(wrapper.label, "a");
+  })
+  .join()
+  .unwrap();
+}
@@ -7,6 +7,13 @@ struct ExampleStruct {
   pub b: i32,
 }
 
+#[derive(Default, DeserializeOver, Debug)]
+#[serde(deny_unknown_fields)]
+struct StrictStruct {
+  #[serde(rename = "type")]
+  pub a: String,
+}
+
 #[test]
 fn works() {
   let json = r#"{ "type": "test" }"#;
@@ -25,8 +32,7 @@ fn works() {
 }
 
 #[test]
-#[should_panic]
-fn old_field_fails() {
+fn old_field_is_ignored() {
   let json = r#"{ "a": "test" }"#;
 
   let mut instance = ExampleStruct {
@@ -35,6 +41,23 @@ fn old_field_fails() {
   };
   let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
 
+  instance
+    .deserialize_over(&mut de)
+    .expect("Failed to deserialize");
+
+  assert_eq!(instance.a, "a string");
+}
+
+#[test]
+#[should_panic]
+fn old_field_is_denied() {
+  let json = r#"{ "a": "test" }"#;
+
+  let mut instance = StrictStruct {
+    a: "a string".to_owned(),
+  };
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+
   instance
     .deserialize_over(&mut de)
     .expect("Failed to deserialize");
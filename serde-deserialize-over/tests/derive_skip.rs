@@ -0,0 +1,95 @@
+mod common;
+
+use common::apply;
+use serde_deserialize_over::DeserializeOver;
+
+/// A runtime-only type which doesn't implement `Deserialize`.
+#[derive(Default, Debug, PartialEq)]
+struct Cache(Vec<u32>);
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Service {
+  name: String,
+  #[serde(skip)]
+  cache: Cache,
+  #[serde(skip_deserializing)]
+  hits: u32,
+}
+
+#[derive(DeserializeOver)]
+struct Handle<T> {
+  id: u32,
+  #[serde(skip)]
+  inner: T,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+struct Pair(u32, #[serde(skip)] Cache, u32);
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum State {
+  Idle,
+  Running {
+    pid: u32,
+    #[serde(skip)]
+    cache: Cache,
+  },
+}
+
+#[test]
+fn skipped_fields_are_untouched() {
+  let mut instance = Service {
+    name: "a".to_owned(),
+    cache: Cache(vec![1, 2]),
+    hits: 5,
+  };
+
+  apply(&mut instance, r#"{ "name": "b" }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    Service {
+      name: "b".to_owned(),
+      cache: Cache(vec![1, 2]),
+      hits: 5,
+    }
+  );
+
+  apply(&mut instance, r#"{ "hits": 0 }"#).expect_err("Deserialized a skipped field");
+  apply(&mut instance, r#"["c"]"#).expect("Failed to deserialize");
+  assert_eq!(instance.name, "c");
+}
+
+#[test]
+fn skipped_generic_needs_no_deserialize() {
+  let mut instance = Handle {
+    id: 1,
+    inner: Cache(vec![3]),
+  };
+
+  apply(&mut instance, r#"{ "id": 2 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.id, 2);
+  assert_eq!(instance.inner, Cache(vec![3]));
+}
+
+#[test]
+fn skipped_tuple_field() {
+  let mut instance = Pair(1, Cache(vec![4]), 2);
+
+  apply(&mut instance, r#"[3, 4]"#).expect("Failed to deserialize");
+  assert_eq!(instance, Pair(3, Cache(vec![4]), 4));
+}
+
+#[test]
+fn skipped_variant_field() {
+  let mut instance = State::Idle;
+
+  apply(&mut instance, r#"{ "Running": { "pid": 7 } }"#).expect("Failed to deserialize");
+  assert_eq!(
+    instance,
+    State::Running {
+      pid: 7,
+      cache: Cache::default(),
+    }
+  );
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::apply;
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Inner {
+  a: i32,
+  b: i32,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+struct Port(u16);
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+struct Limits(#[deserialize_over] Inner);
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+struct Triple(String, #[deserialize_over] Inner, u8);
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+struct Generic<T>(T, T);
+
+#[test]
+fn newtype() {
+  let mut port = Port(80);
+  apply(&mut port, "8080").expect("Failed to deserialize");
+  assert_eq!(port, Port(8080));
+
+  let mut limits = Limits(Inner { a: 1, b: 2 });
+  apply(&mut limits, r#"{ "b": 5 }"#).expect("Failed to deserialize");
+  assert_eq!(limits, Limits(Inner { a: 1, b: 5 }));
+}
+
+#[test]
+fn tuple() {
+  let mut instance = Triple("a".to_owned(), Inner { a: 1, b: 2 }, 3);
+
+  apply(&mut instance, r#"["b", { "a": 4 }]"#).expect("Failed to deserialize");
+  assert_eq!(instance, Triple("b".to_owned(), Inner { a: 4, b: 2 }, 3));
+
+  apply(&mut instance, r#"["c", {}, 4, 5]"#).expect_err("Deserialized too many elements");
+
+  let mut instance = Generic(1u8, 2u8);
+  apply(&mut instance, "[3]").expect("Failed to deserialize");
+  assert_eq!(instance, Generic(3, 2));
+}
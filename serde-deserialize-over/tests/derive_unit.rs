@@ -0,0 +1,46 @@
+mod common;
+
+use common::apply;
+use serde::de::value::{Error, MapDeserializer};
+use serde_deserialize_over::DeserializeOver;
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Marker;
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Outer {
+  #[deserialize_over]
+  marker: Marker,
+  a: i32,
+}
+
+#[test]
+fn works() {
+  let mut instance = Outer::default();
+
+  apply(&mut instance, r#"{ "marker": null, "a": 1 }"#).expect("Failed to deserialize");
+  assert_eq!(instance.a, 1);
+}
+
+#[test]
+fn empty_map() {
+  let mut instance = Marker;
+  let de = MapDeserializer::<_, Error>::new(std::iter::empty::<(&str, i32)>());
+
+  instance
+    .deserialize_over(de)
+    .expect("Failed to deserialize");
+
+  let de = MapDeserializer::<_, Error>::new(std::iter::once(("a", 1)));
+  instance
+    .deserialize_over(de)
+    .expect_err("Deserialized a non-empty map");
+}
+
+#[test]
+fn unexpected_content_fails() {
+  let mut instance = Marker;
+
+  apply(&mut instance, r#"{ "a": 1 }"#).expect_err("Deserialized a non-empty map");
+  apply(&mut instance, r#"5"#).expect_err("Deserialized an integer");
+}
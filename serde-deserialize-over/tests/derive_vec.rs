@@ -0,0 +1,95 @@
+mod common;
+
+use common::changes;
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver};
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Server {
+  host: String,
+  port: u16,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over]
+  default: Vec<Server>,
+  #[deserialize_over(vec = "by_index")]
+  by_index: Vec<Server>,
+  #[deserialize_over(vec = "append")]
+  append: Vec<String>,
+  #[deserialize_over(vec = "replace")]
+  replace: Vec<String>,
+}
+
+fn server(host: &str, port: u16) -> Server {
+  Server {
+    host: host.to_owned(),
+    port,
+  }
+}
+
+#[test]
+fn by_index_merges_elements() {
+  let servers = vec![server("a", 1), server("b", 2)];
+  let mut instance = Config {
+    default: servers.clone(),
+    by_index: servers,
+    ..Default::default()
+  };
+
+  let changes = changes(
+    &mut instance,
+    r#"{
+      "default": [{ "port": 3 }],
+      "by_index": [{}, { "host": "c" }, { "host": "d", "port": 4 }]
+    }"#,
+  );
+
+  assert_eq!(instance.default, [server("a", 3), server("b", 2)]);
+  assert_eq!(
+    instance.by_index,
+    [server("a", 1), server("c", 2), server("d", 4)]
+  );
+  assert_eq!(
+    changes,
+    ["default[0].port", "by_index[1].host", "by_index[2]"]
+  );
+}
+
+#[test]
+fn append_and_replace() {
+  let mut instance = Config {
+    append: vec!["a".to_owned()],
+    replace: vec!["a".to_owned()],
+    ..Default::default()
+  };
+
+  let changes = changes(
+    &mut instance,
+    r#"{ "append": ["b", "c"], "replace": ["d"] }"#,
+  );
+
+  assert_eq!(instance.append, ["a", "b", "c"]);
+  assert_eq!(instance.replace, ["d"]);
+  assert_eq!(changes, ["append[1]", "append[2]", "replace"]);
+}
+
+#[test]
+fn dry_run_leaves_vec_untouched() {
+  let mut instance = Config {
+    default: vec![server("a", 1)],
+    append: vec!["a".to_owned()],
+    ..Default::default()
+  };
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(
+    r#"{ "default": [{ "port": 2 }, { "host": "b" }], "append": ["b"], "replace": 5 }"#,
+  ));
+  instance
+    .deserialize_over_with(&mut de, &mut Context::dry_run())
+    .expect_err("Deserialized a number as a vec");
+
+  assert_eq!(instance.default, [server("a", 1)]);
+  assert_eq!(instance.append, ["a"]);
+}
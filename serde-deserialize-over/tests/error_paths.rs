@@ -0,0 +1,115 @@
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limit {
+  burst: u32,
+  rate: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Limits {
+  #[deserialize_over]
+  per_user: Vec<Limit>,
+  #[deserialize_over]
+  per_route: BTreeMap<String, Limit>,
+  #[deserialize_over]
+  window: (u32, u32),
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  name: String,
+  #[deserialize_over]
+  limits: Limits,
+}
+
+#[derive(DeserializeOver, Debug)]
+#[serde(untagged)]
+enum Either {
+  Number(u32),
+  Text(String),
+}
+
+#[derive(DeserializeOver, Debug)]
+struct Wrapper {
+  #[deserialize_over]
+  either: Either,
+}
+
+fn apply_with_path<T>(target: &mut T, json: &str) -> String
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target
+    .deserialize_over_with_path(&mut de)
+    .unwrap_err()
+    .to_string()
+}
+
+#[test]
+fn errors_include_path() {
+  let mut config = Config::default();
+  config.limits.per_user = vec![Limit::default(); 4];
+  config
+    .limits
+    .per_route
+    .insert("/api".to_owned(), Limit::default());
+
+  let error = apply_with_path(
+    &mut config,
+    r#"{ "limits": { "per_user": [{}, {}, {}, { "burst": "x" }] } }"#,
+  );
+  assert!(
+    error.starts_with("limits.per_user[3].burst: invalid type"),
+    "{}",
+    error
+  );
+
+  let error = apply_with_path(
+    &mut config,
+    r#"{ "limits": { "per_route": { "/api": { "rate": -1 } } } }"#,
+  );
+  assert!(
+    error.starts_with("limits.per_route./api.rate: invalid value"),
+    "{}",
+    error
+  );
+
+  let error = apply_with_path(&mut config, r#"{ "limits": { "window": [1, true] } }"#);
+  assert!(
+    error.starts_with("limits.window[1]: invalid type"),
+    "{}",
+    error
+  );
+}
+
+#[test]
+fn errors_without_path() {
+  let mut config = Config::default();
+
+  let error = apply_with_path(&mut config, r#""config""#);
+  assert!(error.starts_with("invalid type"), "{}", error);
+
+  // Errors from recovered attempts at untagged variants are not reported.
+  let mut wrapper = Wrapper {
+    either: Either::Number(1),
+  };
+  let error = apply_with_path(&mut wrapper, r#"{ "either": [] }"#);
+  assert!(error.starts_with("either: data did not match"), "{}", error);
+}
+
+#[test]
+fn error_path_on_context() {
+  let mut config = Config::default();
+  let mut cx = Context::new().with_error_paths();
+
+  let json = r#"{ "name": 5 }"#;
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  let error = config.deserialize_over_with(&mut de, &mut cx).unwrap_err();
+
+  assert!(error.to_string().starts_with("invalid type"));
+  assert_eq!(cx.error_path(), Some("name"));
+}
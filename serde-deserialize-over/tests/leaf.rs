@@ -0,0 +1,94 @@
+mod common;
+
+use common::apply;
+use serde_deserialize_over::{Context, DeserializeOver};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Server {
+  name: String,
+  #[deserialize_over]
+  port: Option<u16>,
+  #[deserialize_over]
+  addr: [u8; 4],
+  #[deserialize_over]
+  limits: HashMap<String, u32>,
+}
+
+#[test]
+fn leaves_nested_in_containers() {
+  let mut server = Server {
+    name: "main".to_owned(),
+    port: Some(80),
+    addr: [127, 0, 0, 1],
+    limits: vec![("a".to_owned(), 1), ("b".to_owned(), 2)]
+      .into_iter()
+      .collect(),
+  };
+
+  apply(
+    &mut server,
+    r#"{ "port": 8080, "addr": [10, 0], "limits": { "b": 3 } }"#,
+  )
+  .unwrap();
+
+  assert_eq!(server.name, "main");
+  assert_eq!(server.port, Some(8080));
+  assert_eq!(server.addr, [10, 0, 0, 1]);
+  assert_eq!(server.limits["a"], 1);
+  assert_eq!(server.limits["b"], 3);
+}
+
+#[test]
+fn std_value_types() {
+  let mut path = PathBuf::from("/tmp");
+  let mut timeout = Duration::from_secs(1);
+  let mut addr = Ipv4Addr::LOCALHOST;
+  let mut count = NonZeroU32::new(1).unwrap();
+
+  apply(&mut path, r#""/var/lib""#).unwrap();
+  apply(&mut timeout, r#"{ "secs": 5, "nanos": 0 }"#).unwrap();
+  apply(&mut addr, r#""10.0.0.1""#).unwrap();
+  apply(&mut count, "7").unwrap();
+
+  assert_eq!(path, PathBuf::from("/var/lib"));
+  assert_eq!(timeout, Duration::from_secs(5));
+  assert_eq!(addr, Ipv4Addr::new(10, 0, 0, 1));
+  assert_eq!(count.get(), 7);
+
+  assert!(apply(&mut count, "0").is_err());
+  assert_eq!(count.get(), 7);
+}
+
+#[test]
+fn dry_run_and_changes() {
+  let mut server = Server {
+    port: Some(80),
+    ..Default::default()
+  };
+  let json = r#"{ "port": 443, "addr": [1, 2, 3, 4] }"#;
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  server
+    .deserialize_over_with(&mut de, &mut Context::dry_run())
+    .unwrap();
+  assert_eq!(
+    server,
+    Server {
+      port: Some(80),
+      ..Default::default()
+    }
+  );
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  let changes = server.deserialize_over_changes(&mut de).unwrap();
+  assert_eq!(server.port, Some(443));
+  assert_eq!(
+    changes,
+    ["port", "addr[0]", "addr[1]", "addr[2]", "addr[3]"]
+  );
+}
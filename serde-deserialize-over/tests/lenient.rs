@@ -0,0 +1,131 @@
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver, Rejected};
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limit {
+  burst: u32,
+  rate: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  name: String,
+  port: u16,
+  #[deserialize_over]
+  limit: Limit,
+  #[deserialize_over]
+  per_user: Vec<Limit>,
+  #[deserialize_over(on_null = "error")]
+  timeout: Option<u32>,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(untagged)]
+enum Either {
+  Number(u32),
+  Pair { a: u32, b: u32 },
+}
+
+#[derive(DeserializeOver, Debug)]
+struct Wrapper {
+  #[deserialize_over]
+  either: Either,
+  other: u32,
+}
+
+fn apply_lenient<T>(
+  target: &mut T,
+  json: &str,
+) -> serde_json::Result<serde_deserialize_over::Report>
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target.deserialize_over_lenient(&mut de)
+}
+
+fn paths(rejected: &[Rejected]) -> Vec<&str> {
+  rejected.iter().map(|x| x.path.as_str()).collect()
+}
+
+#[test]
+fn applies_valid_fields() {
+  let mut config = Config {
+    per_user: vec![Limit::default(); 2],
+    ..Default::default()
+  };
+
+  let report = apply_lenient(
+    &mut config,
+    r#"{
+      "name": "main",
+      "port": 70000,
+      "limit": { "burst": 5, "rate": "fast" },
+      "per_user": [{ "rate": 1 }, { "burst": true }],
+      "timeout": null
+    }"#,
+  )
+  .unwrap();
+
+  assert_eq!(config.name, "main");
+  assert_eq!(config.port, 0);
+  assert_eq!(config.limit, Limit { burst: 5, rate: 0 });
+  assert_eq!(config.per_user[0].rate, 1);
+
+  assert!(!report.is_clean());
+  assert_eq!(report.applied, ["name", "limit.burst", "per_user[0].rate"]);
+  assert_eq!(
+    paths(&report.rejected),
+    ["port", "limit.rate", "per_user[1].burst", "timeout"]
+  );
+  assert!(report.rejected[0]
+    .to_string()
+    .starts_with("port: invalid value"));
+}
+
+#[test]
+fn input_errors_are_fatal() {
+  let mut config = Config::default();
+
+  assert!(apply_lenient(&mut config, r#"{ "name": "main", "port": 1"#).is_err());
+  assert!(apply_lenient(&mut config, r#""config""#).is_err());
+
+  let report = apply_lenient(&mut config, r#"{ "port": 1 }"#).unwrap();
+  assert!(report.is_clean());
+  assert_eq!(config.port, 1);
+}
+
+#[test]
+fn untagged_variants_are_not_lenient() {
+  let mut wrapper = Wrapper {
+    either: Either::Number(1),
+    other: 0,
+  };
+
+  let report = apply_lenient(
+    &mut wrapper,
+    r#"{ "either": { "a": 1, "b": 2 }, "other": 3 }"#,
+  )
+  .unwrap();
+  assert!(report.is_clean());
+  assert_eq!(wrapper.either, Either::Pair { a: 1, b: 2 });
+
+  let report = apply_lenient(&mut wrapper, r#"{ "either": "x", "other": 4 }"#).unwrap();
+  assert_eq!(paths(&report.rejected), ["either"]);
+  assert_eq!(wrapper.either, Either::Pair { a: 1, b: 2 });
+  assert_eq!(wrapper.other, 4);
+}
+
+#[test]
+fn collecting_errors_on_context() {
+  let mut config = Config::default();
+  let mut cx = Context::new().with_collected_errors();
+
+  let json = r#"{ "name": 5, "port": 2 }"#;
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  config.deserialize_over_with(&mut de, &mut cx).unwrap();
+
+  assert_eq!(config.port, 2);
+  assert_eq!(paths(cx.rejected()), ["name"]);
+  assert!(cx.changes().is_empty());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::changes;
+use serde_derive::Deserialize;
+use serde_deserialize_over::DeserializeOver;
+use std::collections::HashMap;
+
+#[derive(Clone, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Value {
+  on: bool,
+}
+
+fn on(on: bool) -> Value {
+  Value { on }
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over]
+  plain: HashMap<String, Value>,
+  #[deserialize_over(delete_null)]
+  features: HashMap<String, Value>,
+  #[deserialize_over(tombstone = "-")]
+  users: HashMap<String, Value>,
+}
+
+fn map<V: Clone>(entries: &[(&str, V)]) -> HashMap<String, V> {
+  entries
+    .iter()
+    .map(|(k, v)| (k.to_string(), v.clone()))
+    .collect()
+}
+
+fn config() -> Config {
+  Config {
+    plain: map(&[("a", on(true))]),
+    features: map(&[("a", on(true)), ("b", on(false))]),
+    users: map(&[("root", on(true)), ("guest", on(false))]),
+  }
+}
+
+#[test]
+fn null_deletes_entries() {
+  let mut instance = config();
+
+  let changes = changes(&mut instance, r#"{ "features": { "a": null, "c": null } }"#);
+
+  assert_eq!(instance.features, map(&[("b", on(false))]));
+  assert_eq!(changes, ["features.a"]);
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(
+    r#"{ "plain": { "a": null } }"#,
+  ));
+  instance
+    .deserialize_over(&mut de)
+    .expect_err("Deserialized null without delete_null");
+  assert_eq!(instance.plain, map(&[("a", on(true))]));
+}
+
+#[test]
+fn tombstone_deletes_entries() {
+  let mut instance = config();
+
+  let changes = changes(
+    &mut instance,
+    r#"{ "users": { "root": "-", "guest": { "on": true }, "nobody": "-" } }"#,
+  );
+
+  assert_eq!(instance.users, map(&[("guest", on(true))]));
+  assert_eq!(changes, ["users.root", "users.guest.on"]);
+}
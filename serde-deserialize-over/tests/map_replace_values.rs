@@ -0,0 +1,41 @@
+mod common;
+
+use common::changes;
+use serde_deserialize_over::DeserializeOver;
+use std::collections::HashMap;
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over(replace_values)]
+  limits: HashMap<String, i32>,
+  #[deserialize_over(replace_values, delete_null)]
+  names: HashMap<String, String>,
+}
+
+#[test]
+fn values_are_replaced() {
+  let mut instance = Config::default();
+  instance.limits.insert("a".to_owned(), 1);
+  instance.limits.insert("b".to_owned(), 2);
+
+  let changes = changes(&mut instance, r#"{ "limits": { "b": 3, "c": 4 } }"#);
+
+  assert_eq!(instance.limits.len(), 3);
+  assert_eq!(instance.limits["a"], 1);
+  assert_eq!(instance.limits["b"], 3);
+  assert_eq!(instance.limits["c"], 4);
+  assert_eq!(changes, ["limits.b", "limits.c"]);
+}
+
+#[test]
+fn combined_with_delete_null() {
+  let mut instance = Config::default();
+  instance.names.insert("a".to_owned(), "x".to_owned());
+  instance.names.insert("b".to_owned(), "y".to_owned());
+
+  let changes = changes(&mut instance, r#"{ "names": { "a": null, "b": "z" } }"#);
+
+  assert_eq!(instance.names.len(), 1);
+  assert_eq!(instance.names["b"], "z");
+  assert_eq!(changes, ["names.a", "names.b"]);
+}
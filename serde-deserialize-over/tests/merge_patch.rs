@@ -0,0 +1,141 @@
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver};
+use std::collections::HashMap;
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limits {
+  max: u32,
+  min: u32,
+}
+
+fn default_name() -> String {
+  "default".to_owned()
+}
+
+#[derive(Default, DeserializeOver, Debug, PartialEq)]
+struct Config {
+  #[serde(default = "default_name")]
+  name: String,
+  #[serde(default)]
+  retries: u32,
+  port: u16,
+  comment: Option<String>,
+  #[deserialize_over]
+  limits: Option<Limits>,
+  #[deserialize_over]
+  users: HashMap<String, Limits>,
+  #[deserialize_over]
+  servers: Vec<Limits>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Id(u32);
+
+#[derive(DeserializeOver, Debug)]
+struct Account {
+  id: Id,
+}
+
+fn config() -> Config {
+  let mut users = HashMap::new();
+  users.insert("root".to_owned(), Limits { max: 1, min: 0 });
+  users.insert("guest".to_owned(), Limits { max: 2, min: 0 });
+
+  Config {
+    name: "a".to_owned(),
+    retries: 3,
+    port: 80,
+    comment: Some("comment".to_owned()),
+    limits: Some(Limits { max: 5, min: 1 }),
+    users,
+    servers: vec![Limits { max: 1, min: 1 }, Limits { max: 2, min: 2 }],
+  }
+}
+
+fn patch(target: &mut Config, json: &str) -> serde_json::Result<()> {
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target.deserialize_merge_patch(&mut de)
+}
+
+#[test]
+fn null_removes_and_resets() {
+  let mut instance = config();
+
+  patch(
+    &mut instance,
+    r#"{
+      "name": null,
+      "retries": null,
+      "comment": null,
+      "limits": null,
+      "users": { "guest": null, "nobody": null, "root": { "max": 7 } }
+    }"#,
+  )
+  .expect("Failed to apply merge patch");
+
+  assert_eq!(instance.name, "default");
+  assert_eq!(instance.retries, 0);
+  assert_eq!(instance.port, 80);
+  assert_eq!(instance.comment, None);
+  assert_eq!(instance.limits, None);
+  assert_eq!(instance.users.len(), 1);
+  assert_eq!(instance.users["root"], Limits { max: 7, min: 0 });
+}
+
+#[test]
+fn null_resets_to_type_default() {
+  let mut instance = config();
+
+  patch(&mut instance, r#"{ "port": null, "servers": null }"#)
+    .expect("Failed to apply merge patch");
+  assert_eq!(instance.port, 0);
+  assert!(instance.servers.is_empty());
+
+  let mut account = Account { id: Id(1) };
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(r#"{ "id": null }"#));
+  let err = account
+    .deserialize_merge_patch(&mut de)
+    .expect_err("Reset a field without a default");
+  assert!(err.to_string().contains("no default"), "{}", err);
+  assert_eq!(account.id, Id(1));
+}
+
+#[test]
+fn arrays_are_replaced() {
+  let mut instance = config();
+
+  patch(&mut instance, r#"{ "servers": [{ "max": 7 }] }"#)
+    .expect_err("Merged an array element by index");
+
+  patch(&mut instance, r#"{ "servers": [{ "max": 7, "min": 0 }] }"#)
+    .expect("Failed to apply merge patch");
+  assert_eq!(instance.servers, [Limits { max: 7, min: 0 }]);
+}
+
+#[test]
+fn null_is_ignored_outside_merge_patches() {
+  let mut instance = config();
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(
+    r#"{ "limits": null, "users": { "guest": null } }"#,
+  ));
+  instance
+    .deserialize_over(&mut de)
+    .expect_err("Deserialized null as a map value");
+  assert_eq!(instance.limits, config().limits);
+}
+
+#[test]
+fn merge_patch_reports_changes() {
+  let mut instance = config();
+  let mut cx = Context::tracking_changes().with_merge_patch();
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(
+    r#"{ "retries": null, "limits": null, "users": { "guest": null } }"#,
+  ));
+  instance
+    .deserialize_over_with(&mut de, &mut cx)
+    .expect("Failed to apply merge patch");
+
+  assert_eq!(cx.changes(), ["retries", "limits", "users.guest"]);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::apply;
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Node {
+  value: u32,
+  #[deserialize_over]
+  next: Option<Box<Node>>,
+}
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Settings {
+  a: u32,
+  b: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over]
+  shared: Arc<Settings>,
+  #[deserialize_over]
+  local: Rc<Settings>,
+  #[deserialize_over]
+  cell: RefCell<Settings>,
+  #[deserialize_over]
+  mutex: Mutex<Settings>,
+  #[deserialize_over]
+  rwlock: RwLock<Settings>,
+}
+
+#[test]
+fn recursive_box() {
+  let mut node = Node {
+    value: 1,
+    next: Some(Box::new(Node::default())),
+  };
+
+  apply(
+    &mut node,
+    r#"{ "next": { "value": 2, "next": { "value": 3 } } }"#,
+  )
+  .expect("Failed to deserialize");
+
+  let next = node.next.as_ref().unwrap();
+  assert_eq!(node.value, 1);
+  assert_eq!(next.value, 2);
+  assert_eq!(next.next.as_ref().unwrap().value, 3);
+}
+
+#[test]
+fn shared_pointers_copy_on_write() {
+  let mut config = Config::default();
+  let other = config.shared.clone();
+
+  apply(
+    &mut config,
+    r#"{ "shared": { "a": 1 }, "local": { "b": 2 } }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(config.shared.a, 1);
+  assert_eq!(other.a, 0);
+  assert_eq!(config.local.b, 2);
+
+  // A dry run doesn't unshare the value.
+  let other = config.shared.clone();
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(
+    r#"{ "shared": { "a": 5 }, "local": { "a": "x" } }"#,
+  ));
+  config
+    .deserialize_over_with(&mut de, &mut Context::dry_run())
+    .expect_err("Deserialized a string as a number");
+  assert!(Arc::ptr_eq(&config.shared, &other));
+}
+
+#[test]
+fn cells_and_locks() {
+  let mut config = Config::default();
+
+  apply(
+    &mut config,
+    r#"{ "cell": { "a": 1 }, "mutex": { "a": 2 }, "rwlock": { "a": 3 } }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(config.cell.borrow().a, 1);
+  assert_eq!(config.mutex.lock().unwrap().a, 2);
+  assert_eq!(config.rwlock.read().unwrap().a, 3);
+}
+
+#[test]
+fn poisoned_lock_fails() {
+  let mut config = Config::default();
+
+  let mutex = &config.mutex;
+  std::thread::scope(|scope| {
+    scope
+      .spawn(|| {
+        let _guard = mutex.lock().unwrap();
+        panic!("poison the mutex");
+      })
+      .join()
+      .expect_err("Thread didn't panic");
+  });
+
+  let err = apply(&mut config, r#"{ "mutex": { "a": 2 } }"#).expect_err("Used a poisoned mutex");
+  assert!(err.to_string().contains("poisoned"), "{}", err);
+}
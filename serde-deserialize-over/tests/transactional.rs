@@ -0,0 +1,196 @@
+use serde::Deserializer;
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver};
+use std::collections::HashMap;
+
+#[derive(Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limits {
+  max: u32,
+  min: u32,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum Backend {
+  Memory,
+  File { path: String },
+}
+
+#[derive(Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Server {
+  name: String,
+  port: u16,
+}
+
+// None of these types implement `Clone`, which `try_deserialize_over` doesn't
+// need.
+#[derive(DeserializeOver, Debug, PartialEq)]
+struct Config {
+  name: String,
+  #[deserialize_over]
+  limits: Limits,
+  #[deserialize_over]
+  backend: Backend,
+  #[deserialize_over]
+  users: HashMap<String, Limits>,
+  #[deserialize_over(merge_by = "name", tombstone = "_delete")]
+  servers: Vec<Server>,
+}
+
+/// A type with a manual impl which doesn't support dry runs.
+#[derive(Default)]
+struct Manual(u32);
+
+impl<'de> DeserializeOver<'de> for Manual {
+  fn deserialize_over<D>(&mut self, de: D) -> Result<(), D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    self.0 = serde::Deserialize::deserialize(de)?;
+    Ok(())
+  }
+}
+
+#[derive(Default, DeserializeOver)]
+struct WithManual {
+  #[deserialize_over]
+  manual: Manual,
+}
+
+fn config() -> Config {
+  Config {
+    name: "a".to_owned(),
+    limits: Limits { max: 10, min: 1 },
+    backend: Backend::Memory,
+    users: HashMap::new(),
+    servers: vec![Server {
+      name: "a".to_owned(),
+      port: 80,
+    }],
+  }
+}
+
+fn try_apply<T>(target: &mut T, json: &str) -> serde_json::Result<()>
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target.try_deserialize_over(&mut de)
+}
+
+#[test]
+fn failure_leaves_target_untouched() {
+  let mut instance = config();
+
+  try_apply(
+    &mut instance,
+    r#"{
+      "name": "b",
+      "limits": { "max": 20 },
+      "users": { "root": { "max": 1, "min": 0 } },
+      "backend": { "File": {} }
+    }"#,
+  )
+  .expect_err("Deserialized a variant with missing fields");
+  assert_eq!(instance, config());
+
+  try_apply(
+    &mut instance,
+    r#"{ "name": "b", "limits": { "max": 20, "min": "x" } }"#,
+  )
+  .expect_err("Deserialized a string as a number");
+  assert_eq!(instance, config());
+}
+
+#[test]
+fn success_applies_everything() {
+  let mut instance = config();
+
+  try_apply(
+    &mut instance,
+    r#"{
+      "name": "b",
+      "limits": { "max": 20 },
+      "users": { "root": { "max": 1, "min": 0 } },
+      "backend": { "File": { "path": "/tmp" } }
+    }"#,
+  )
+  .expect("Failed to deserialize");
+
+  assert_eq!(instance.name, "b");
+  assert_eq!(instance.limits, Limits { max: 20, min: 1 });
+  assert_eq!(instance.users["root"], Limits { max: 1, min: 0 });
+  assert_eq!(
+    instance.backend,
+    Backend::File {
+      path: "/tmp".to_owned()
+    }
+  );
+}
+
+#[test]
+fn merge_by_failure_leaves_target_untouched() {
+  let mut instance = config();
+
+  // Deleting `a` succeeds, re-adding it without a port does not.
+  let err = try_apply(
+    &mut instance,
+    r#"{ "servers": [{ "name": "a", "_delete": true }, { "name": "a" }] }"#,
+  )
+  .expect_err("Deserialized a server with a missing port");
+  assert!(err.to_string().contains("missing field `port`"), "{}", err);
+  assert_eq!(instance, config());
+}
+
+#[test]
+fn merge_by_matches_plain_deserialize_over() {
+  // The second element merges into the one added by the first.
+  let json = r#"{ "servers": [{ "name": "c", "port": 1 }, { "name": "c" }] }"#;
+
+  let mut instance = config();
+  try_apply(&mut instance, json).expect("Failed to deserialize");
+
+  let mut expected = config();
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  expected
+    .deserialize_over(&mut de)
+    .expect("Failed to deserialize");
+
+  assert_eq!(instance, expected);
+  assert_eq!(instance.servers.len(), 2);
+}
+
+#[test]
+fn errors_include_their_path() {
+  let mut instance = config();
+
+  let err = try_apply(
+    &mut instance,
+    r#"{ "servers": [{ "name": "a", "port": "x" }] }"#,
+  )
+  .expect_err("Deserialized a string as a number");
+  assert!(
+    err.to_string().starts_with("servers.a.port: invalid type"),
+    "{}",
+    err
+  );
+  assert_eq!(instance, config());
+}
+
+#[test]
+fn manual_impls_cannot_dry_run() {
+  let mut instance = WithManual::default();
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(r#"{ "manual": 5 }"#));
+  let err = instance
+    .deserialize_over_with(&mut de, &mut Context::dry_run())
+    .expect_err("Dry run of a type without support succeeded");
+  assert!(
+    err.to_string().contains("does not support dry runs"),
+    "{}",
+    err
+  );
+  assert_eq!(instance.manual.0, 0);
+
+  try_apply(&mut instance, r#"{ "manual": 5 }"#).expect_err("Applied a type without dry runs");
+  assert_eq!(instance.manual.0, 0);
+}
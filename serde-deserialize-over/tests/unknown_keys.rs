@@ -0,0 +1,116 @@
+use serde_derive::Deserialize;
+use serde_deserialize_over::{DeserializeOver, UnknownKey};
+use std::collections::HashMap;
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Limit {
+  burst: u32,
+  rate: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  name: String,
+  #[deserialize_over]
+  limit: Limit,
+  #[deserialize_over]
+  per_user: Vec<Limit>,
+  #[deserialize_over]
+  per_route: HashMap<String, Limit>,
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+#[serde(untagged)]
+enum Shape {
+  Square { side: u32 },
+  Rect { width: u32, height: u32 },
+}
+
+fn unknown_keys<T>(target: &mut T, json: &str) -> Vec<UnknownKey>
+where
+  T: for<'de> DeserializeOver<'de>,
+{
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  target.deserialize_over_unknown_keys(&mut de).unwrap()
+}
+
+fn paths(unknown: &[UnknownKey]) -> Vec<&str> {
+  unknown.iter().map(|x| x.path.as_str()).collect()
+}
+
+#[test]
+fn reports_nested_unknown_keys() {
+  let mut config = Config {
+    per_user: vec![Limit::default()],
+    per_route: vec![("/api".to_owned(), Limit::default())]
+      .into_iter()
+      .collect(),
+    ..Default::default()
+  };
+
+  let unknown = unknown_keys(
+    &mut config,
+    r#"{
+      "nmae": "main",
+      "limit": { "brust": 1, "rate": 2 },
+      "per_user": [{ "rates": 3 }],
+      "per_route": { "/api": { "other": 4 } }
+    }"#,
+  );
+
+  assert_eq!(
+    paths(&unknown),
+    [
+      "nmae",
+      "limit.brust",
+      "per_user[0].rates",
+      "per_route./api.other"
+    ]
+  );
+  assert_eq!(config.limit, Limit { burst: 0, rate: 2 });
+  assert_eq!(config.per_user[0], Limit::default());
+}
+
+#[test]
+fn suggests_closest_field() {
+  let mut config = Config::default();
+  let unknown = unknown_keys(&mut config, r#"{ "nmae": "a", "limt": {}, "xyz": 1 }"#);
+
+  let suggestions = unknown.iter().map(|x| x.suggestion).collect::<Vec<_>>();
+  assert_eq!(suggestions, [Some("name"), Some("limit"), None]);
+  assert_eq!(
+    unknown[0].to_string(),
+    "unknown field `nmae`, did you mean `name`?"
+  );
+  assert_eq!(unknown[2].to_string(), "unknown field `xyz`");
+}
+
+#[derive(DeserializeOver, Debug, PartialEq)]
+enum Mode {
+  Fixed { rate: u32 },
+  Burst { burst: u32 },
+}
+
+#[test]
+fn failed_untagged_attempts_are_forgotten() {
+  // Merging into the current variant fails after seeing the unknown `side`
+  // key, so the input is used to build a new variant instead. Only the key
+  // that the new variant ignored is reported.
+  let mut shape = Shape::Rect {
+    width: 1,
+    height: 1,
+  };
+  let unknown = unknown_keys(&mut shape, r#"{ "side": 2, "width": "wide" }"#);
+
+  assert_eq!(shape, Shape::Square { side: 2 });
+  assert_eq!(paths(&unknown), ["width"]);
+}
+
+#[test]
+fn fresh_variants_report_unknown_keys() {
+  let mut mode = Mode::Fixed { rate: 1 };
+  let unknown = unknown_keys(&mut mode, r#"{ "Burst": { "burst": 2, "rate": 3 } }"#);
+
+  assert_eq!(mode, Mode::Burst { burst: 2 });
+  assert_eq!(paths(&unknown), ["rate"]);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::apply;
+use serde_derive::Deserialize;
+use serde_deserialize_over::{Context, DeserializeOver, Merge, Replace};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Default, Deserialize, Debug, PartialEq)]
+struct Foreign {
+  a: u32,
+  #[serde(default)]
+  b: u32,
+}
+
+#[derive(Clone, Default, Deserialize, DeserializeOver, Debug, PartialEq)]
+struct Local {
+  a: u32,
+  b: u32,
+}
+
+#[derive(Default, DeserializeOver, Debug)]
+struct Config {
+  #[deserialize_over]
+  foreign: BTreeMap<String, Replace<Foreign>>,
+  #[deserialize_over]
+  local: BTreeMap<String, Merge<Local>>,
+}
+
+#[test]
+fn replace_and_merge_values() {
+  let mut config = Config::default();
+  config
+    .foreign
+    .insert("x".to_owned(), Replace(Foreign { a: 1, b: 2 }));
+  config
+    .local
+    .insert("x".to_owned(), Merge(Local { a: 1, b: 2 }));
+
+  apply(
+    &mut config,
+    r#"{
+      "foreign": { "x": { "a": 3 }, "y": { "a": 4 } },
+      "local": { "x": { "a": 3 }, "y": { "a": 4, "b": 5 } }
+    }"#,
+  )
+  .unwrap();
+
+  assert_eq!(config.foreign["x"].0, Foreign { a: 3, b: 0 });
+  assert_eq!(config.foreign["y"].0, Foreign { a: 4, b: 0 });
+  assert_eq!(config.local["x"].0, Local { a: 3, b: 2 });
+  assert_eq!(config.local["y"].0, Local { a: 4, b: 5 });
+}
+
+#[test]
+fn replace_respects_dry_run() {
+  let mut value = Replace(Foreign { a: 1, b: 2 });
+  let json = r#"{ "a": 3 }"#;
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  value
+    .deserialize_over_with(&mut de, &mut Context::dry_run())
+    .unwrap();
+  assert_eq!(value.a, 1);
+
+  let mut de = serde_json::Deserializer::new(serde_json::de::StrRead::new(json));
+  let changes = value.deserialize_over_changes(&mut de).unwrap();
+  assert_eq!(value.into_inner(), Foreign { a: 3, b: 0 });
+  assert_eq!(changes, [""]);
+}